@@ -0,0 +1,91 @@
+//! The diffusion network in the feedback loop. A chain of allpass filters smears every repeat a
+//! little more on each pass, so the echoes blur into a reverb-like wash. Slowly modulated delay
+//! times keep the wash from ringing metallic, and with Width the right channel's chain runs longer
+//! than the left one's, so the tail spreads out across the stereo field.
+
+use crate::linear_interpolate;
+
+const NUM_STAGES: usize = 4;
+/// The stages' delay times at the default size, in milliseconds. None of them are multiples of
+/// each other, so their echoes don't pile up.
+const STAGE_DELAYS_MS: [f32; NUM_STAGES] = [4.7, 7.9, 11.3, 17.3];
+/// Every stage's delay time modulates at its own rate, in Hz.
+const MODULATION_RATES_HZ: [f32; NUM_STAGES] = [0.31, 0.43, 0.57, 0.73];
+/// How far the delay times move at full Modulation, in milliseconds.
+const MAX_MODULATION_MS: f32 = 1.0;
+/// Size scales the delay times from this fraction to this multiple of the default.
+const MIN_SIZE_SCALE: f32 = 0.25;
+const MAX_SIZE_SCALE: f32 = 2.0;
+/// How much longer the right channel's delay times are at full stereo width.
+pub const STEREO_SPREAD: f32 = 0.25;
+/// The allpass filters' coefficient. Higher values smear more, but start to ring.
+const ALLPASS_GAIN: f32 = 0.6;
+
+#[derive(Default)]
+struct AllpassStage {
+    buffer: Vec<f32>,
+    write_pos: usize,
+    /// The delay time modulation's position in its cycle, from 0 to 1.
+    phase: f32,
+}
+
+#[derive(Default)]
+pub struct Diffuser {
+    stages: [AllpassStage; NUM_STAGES],
+    sample_rate: f32,
+}
+
+impl Diffuser {
+    /// Makes room for the longest delay times at this sample rate and clears the stages.
+    pub fn reset(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        for (stage, delay_ms) in self.stages.iter_mut().zip(STAGE_DELAYS_MS) {
+            let max_delay_ms = delay_ms * MAX_SIZE_SCALE * (1.0 + STEREO_SPREAD) + MAX_MODULATION_MS;
+            let len = (max_delay_ms * 0.001 * sample_rate) as usize + 4;
+            stage.buffer = vec![0.0; len];
+            stage.write_pos = 0;
+            stage.phase = 0.0;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for stage in &mut self.stages {
+            stage.buffer.fill(0.0);
+        }
+    }
+
+    /// Runs a sample through the chain. `scale` stretches the delay times, see [`size_scale()`],
+    /// `modulation` goes from 0 to 1, and `phase_offset` shifts the modulation's cycles so the two
+    /// channels don't move together.
+    #[inline]
+    pub fn process(&mut self, input: f32, scale: f32, modulation: f32, phase_offset: f32) -> f32 {
+        let samples_per_ms = 0.001 * self.sample_rate;
+        let mut signal = input;
+        let stages = self.stages.iter_mut().zip(STAGE_DELAYS_MS).zip(MODULATION_RATES_HZ);
+        for ((stage, delay_ms), rate) in stages {
+            let len = stage.buffer.len();
+            if len == 0 {
+                return input;
+            }
+
+            stage.phase = (stage.phase + rate / self.sample_rate).fract();
+            let wobble = (std::f32::consts::TAU * (stage.phase + phase_offset)).sin();
+            let delay_ms = delay_ms * scale + wobble * modulation * MAX_MODULATION_MS;
+            let delay_samples = (delay_ms * samples_per_ms).clamp(1.0, len as f32 - 2.0);
+            let read_pos = (stage.write_pos as f32 - delay_samples).rem_euclid(len as f32);
+            let delayed = linear_interpolate(&stage.buffer, read_pos);
+
+            let fed_in = signal + ALLPASS_GAIN * delayed;
+            stage.buffer[stage.write_pos] = fed_in;
+            stage.write_pos = (stage.write_pos + 1) % len;
+            signal = delayed - ALLPASS_GAIN * fed_in;
+        }
+
+        signal
+    }
+}
+
+/// The factor for the stages' delay times at a Size from 0 to 1.
+pub fn size_scale(size: f32) -> f32 {
+    MIN_SIZE_SCALE * (MAX_SIZE_SCALE / MIN_SIZE_SCALE).powf(size)
+}
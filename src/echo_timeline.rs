@@ -0,0 +1,115 @@
+//! The expected echo train for the current settings, for the editor's echo timeline. A single tone
+//! is followed through the feedback loop with the same gain staging, saturation and tone filter as
+//! the audio thread uses.
+
+use nih_plug::prelude::*;
+
+use crate::{
+    calculate_tape_constants, delay_time_seconds, drive_tape_classic, makeup_gain, TapeParams,
+};
+
+/// Echoes quieter than this are left out.
+pub const FLOOR_DB: f32 = -60.0;
+/// The loudest level the timeline can show.
+pub const CEILING_DB: f32 = 6.0;
+const MAX_ECHOES: usize = 48;
+/// The longest stretch of time the timeline covers, in seconds.
+const MAX_WINDOW_SECONDS: f32 = 10.0;
+/// The level of the tone going into the delay.
+const INPUT_LEVEL: f32 = 0.5;
+/// The frequency the tone filter's attenuation is evaluated at.
+const REFERENCE_HZ: f32 = 1000.0;
+/// The audio thread keeps this many samples of headroom in the delay buffer.
+const BUFFER_HEADROOM_SAMPLES: f32 = 100.0;
+/// The delay buffer holds this many seconds of audio.
+const BUFFER_SECONDS: f32 = 2.0;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EchoTrain {
+    /// Each repeat's time in seconds and level in decibels relative to the input.
+    pub echoes: Vec<(f32, f32)>,
+    /// The length of time the timeline covers, in seconds.
+    pub window_seconds: f32,
+    /// Musical grid lines for when Time Sync is on.
+    pub grid: Option<EchoGrid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EchoGrid {
+    /// The length of the selected note division, in seconds.
+    pub division_seconds: f32,
+    /// The length of a quarter note at the host's tempo, in seconds.
+    pub beat_seconds: f32,
+}
+
+/// Computes the echo train for the current parameter values. Tape Only mode bypasses the delay, and
+/// in Resonator mode the repeats follow each other too closely to show, so there are no echoes
+/// then.
+pub(crate) fn echo_train(params: &TapeParams, tempo: f32, sample_rate: f32) -> EchoTrain {
+    let is_time_sync = params.time_sync.value();
+    let grid = is_time_sync.then(|| EchoGrid {
+        division_seconds: delay_time_seconds(
+            params,
+            params.delay_time_ms.value(),
+            params.division.value(),
+            true,
+            tempo,
+        )
+        .0,
+        beat_seconds: 60.0 / tempo,
+    });
+    if params.distortion_mode.value() || params.resonator.value() {
+        return EchoTrain {
+            echoes: Vec::new(),
+            window_seconds: 1.0,
+            grid,
+        };
+    }
+
+    let max_delay_seconds = BUFFER_SECONDS - BUFFER_HEADROOM_SAMPLES / sample_rate;
+    let delay_seconds = delay_time_seconds(
+        params,
+        params.delay_time_ms.value(),
+        params.division.value(),
+        is_time_sync,
+        tempo,
+    )
+    .0
+    .clamp(1.0 / sample_rate, max_delay_seconds);
+    // Swing pushes every odd repeat late by this much
+    let swing_seconds = (params.swing.value() * 2.0 - 1.0) * delay_seconds;
+
+    let gain = params.gain.value();
+    let makeup_gain = makeup_gain(gain);
+    let feedback_gain = (params.feedback.value() * 1.2) / gain.sqrt();
+    let mix = params.mix.value();
+
+    // The magnitude response of the one-pole lowpass in the feedback path
+    let cutoff = calculate_tape_constants(sample_rate, params.broken_tape.value()).current_tone_cutoff;
+    let omega = std::f32::consts::TAU * REFERENCE_HZ / sample_rate;
+    let filter_gain =
+        cutoff / (1.0 - 2.0 * (1.0 - cutoff) * omega.cos() + (1.0 - cutoff).powi(2)).sqrt();
+
+    let mut echoes = Vec::new();
+    let mut recorded = drive_tape_classic(gain, INPUT_LEVEL).abs();
+    for repeat in 1..=MAX_ECHOES {
+        let time = repeat as f32 * delay_seconds + if repeat % 2 == 1 { swing_seconds } else { 0.0 };
+        if time > MAX_WINDOW_SECONDS {
+            break;
+        }
+
+        let level_db = util::gain_to_db(recorded * makeup_gain * mix / INPUT_LEVEL);
+        if level_db < FLOOR_DB {
+            break;
+        }
+        echoes.push((time, level_db));
+
+        recorded = drive_tape_classic(gain, recorded * filter_gain * feedback_gain).abs();
+    }
+
+    EchoTrain {
+        window_seconds: ((echoes.len() + 1) as f32 * delay_seconds).min(MAX_WINDOW_SECONDS),
+        echoes,
+        grid,
+    }
+}
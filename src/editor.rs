@@ -4,52 +4,469 @@ use crate::nih_log;
 use crate::AtomicF32;
 use crate::BoolParam;
 use nih_plug::prelude::Param;
-use nih_plug::prelude::{util, Editor};
+use nih_plug::prelude::{Editor, ParamPtr};
 use nih_plug_vizia::assets::register_noto_sans_light;
 use nih_plug_vizia::vizia::prelude::*;
 use nih_plug_vizia::widgets::ResizeHandle;
+use nih_plug_vizia::widgets::{ParamSlider, ParamSliderExt, ParamSliderStyle};
 use nih_plug_vizia::widgets::{RawParamEvent,ParamEvent};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use crate::editor::my_peak_meter::MyPeakMeter;
+use crate::echo_timeline;
+use crate::host_info::HostInfo;
+use crate::envelope::ENVELOPE_TARGETS;
+use crate::lfo::{LfoParams, LfoTarget, NUM_LFOS};
+use crate::meters::MeterLevels;
+use crate::midi_learn::{self, MidiMappings};
+use crate::presets;
+use crate::randomizer::RandomizerSettings;
+use crate::settings::{self, MeterStyle, Settings};
+use crate::snapshot::{AbSlot, ParamSnapshot};
+use crate::spectrum::SpectrumRing;
+use crate::tape_overview::TapeOverview;
+use crate::transport::TransportOptions;
+use crate::update_check::UpdateStatus;
 use crate::TapeParams;
-use nih_plug_vizia::vizia::image::load_from_memory;
 use nih_plug_vizia::{create_vizia_editor, ViziaState, ViziaTheming};
 
+use self::echo_view::EchoView;
+use self::history::UndoHistory;
+use self::saturation_view::SaturationView;
+use self::skin::SkinStyle;
+use self::spectrum_view::SpectrumView;
+use self::tape_view::TapeView;
+use self::vu_meter::VuMeter;
 use self::param_knob::ParamKnob;
 
+mod echo_view;
+mod history;
+mod knob_input;
 mod my_peak_meter;
 mod param_knob;
+mod saturation_view;
 mod single_knob;
+mod skin;
+mod spectrum_view;
+mod tape_view;
+mod vu_meter;
 
 pub const ORBITRON_TTF: &[u8] = include_bytes!("resource/fonts/Orbitron-Regular.ttf");
 pub const COMFORTAA_LIGHT_TTF: &[u8] = include_bytes!("resource/fonts/Comfortaa-Light.ttf");
 pub const COMFORTAA: &str = "Comfortaa";
 
-const BG_IMAGE_BYTES: &[u8] = include_bytes!("resource/images/background_image.png");
-const INSTA_ICON_BYTES: &[u8] = include_bytes!("resource/images/instagram_icon.png");
-const SPOTIFY_ICON_BYTES: &[u8] = include_bytes!("resource/images/spotify_icon.png");
+const RELEASES_URL: &str = "https://github.com/minburg/vst-tape-delay/releases";
+
+/// How often the selected skin's folder is checked for changes.
+const SKIN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How often incoming MIDI CCs are applied to the parameters.
+const MIDI_POLL_INTERVAL: Duration = Duration::from_millis(15);
+/// The width of the MIDI learn menu, used to keep it inside the window.
+const MIDI_MENU_WIDTH: f32 = 220.0;
 
 #[derive(Lens)]
 struct Data {
     tape_data: Arc<TapeParams>,
-    peak_meter_l: Arc<AtomicF32>,
-    peak_meter_r: Arc<AtomicF32>,
+    meter_levels: Arc<MeterLevels>,
+    drive_level: Arc<AtomicF32>,
+    tape_overview: Arc<TapeOverview>,
+    spectrum_ring: Arc<SpectrumRing>,
+    preset_name: String,
+    is_slot_b_active: bool,
+    #[lens(ignore)]
+    history: UndoHistory,
+    can_undo: bool,
+    can_redo: bool,
+    has_morph_start: bool,
+    has_morph_end: bool,
+    randomizer: RandomizerSettings,
+    update_status: Arc<UpdateStatus>,
+    settings: Settings,
+    #[lens(ignore)]
+    skin: SkinStyle,
+    /// The skin folder's modification time when the skin was last loaded.
+    #[lens(ignore)]
+    skin_modified: Option<SystemTime>,
+    /// A copy of the CC mappings stored in the parameters, for the MIDI learn menu.
+    midi_mappings: MidiMappings,
+    /// A copy of the Tap note stored in the parameters, for the MIDI learn menu.
+    tap_note: Option<u8>,
+    midi_menu: Option<MidiMenu>,
+    /// What gets assigned to the next incoming CC or note.
+    midi_learn_target: Option<MidiTarget>,
+    /// A copy of the transport options stored in the parameters.
+    transport_options: TransportOptions,
+    #[lens(ignore)]
+    host_info: Arc<HostInfo>,
+    /// Time Sync follows the host's tempo, so there's nothing to tap.
+    is_tap_ignored: bool,
+}
+
+/// The MIDI learn menu, opened by right-clicking a knob, a toggle or the Tap button.
+#[derive(Debug, Clone, PartialEq)]
+struct MidiMenu {
+    target: MidiTarget,
+    name: String,
+    /// Where the menu opens, in logical pixels.
+    position: (f32, f32),
+}
+
+/// What MIDI learn assigns. Parameters are controlled by a CC, the Tap button by a note.
+#[derive(Debug, Clone, PartialEq)]
+enum MidiTarget {
+    Param(String),
+    TapNote,
+}
+
+impl nih_plug_vizia::vizia::prelude::Data for RandomizerSettings {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl nih_plug_vizia::vizia::prelude::Data for Settings {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl nih_plug_vizia::vizia::prelude::Data for MeterStyle {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl nih_plug_vizia::vizia::prelude::Data for MidiMappings {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl nih_plug_vizia::vizia::prelude::Data for MidiMenu {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
 }
 
-impl Model for Data {}
+impl nih_plug_vizia::vizia::prelude::Data for MidiTarget {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl nih_plug_vizia::vizia::prelude::Data for TransportOptions {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+#[derive(Debug)]
+enum EditorEvent {
+    LoadPreset(usize),
+    /// Loads the default preset into a fresh instance. Unlike [`EditorEvent::LoadPreset`], this
+    /// doesn't end up in the undo history.
+    LoadDefaultPreset(usize),
+    SelectSlot(AbSlot),
+    CopyAToB,
+    SwapSlots,
+    Undo,
+    Redo,
+    /// Stores the current settings as the Morph start (`false`) or end (`true`) snapshot.
+    StoreMorphSnapshot(bool),
+    Randomize,
+    SetRandomizerIntensity(f32),
+    ToggleRandomizerLock(usize),
+    /// Sets the lower (`false`) or upper (`true`) end of a parameter's randomizer range.
+    SetRandomizerRange(usize, bool, f32),
+    ChangeSettings(Settings),
+    SetTransportOptions(TransportOptions),
+    /// Reloads the skin if any of its files changed.
+    PollSkin,
+    /// Opens the MIDI learn menu for a parameter at the mouse cursor.
+    OpenMidiMenu(ParamPtr),
+    /// Opens the MIDI learn menu for the Tap button at the mouse cursor.
+    OpenTapMenu,
+    /// Closes the MIDI learn menu, cancelling MIDI learn if it's still waiting for a CC.
+    CloseMidiMenu,
+    /// Assigns the next incoming CC or note to the target of the MIDI learn menu.
+    StartMidiLearn,
+    /// Removes the assignment of the target of the MIDI learn menu.
+    ForgetMidiMapping,
+    /// Applies the CCs and the tapped delay time that came in since the last poll.
+    PollMidi,
+    /// Taps the tempo. The audio thread times the taps.
+    Tap,
+}
+
+impl Model for Data {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|editor_event, _| match editor_event {
+            EditorEvent::LoadPreset(index) => {
+                if let Some(preset) = presets::factory_bank().get(*index) {
+                    set_normalized_values(cx, &preset.normalized_values(&self.tape_data));
+                    self.preset_name = preset.name.clone();
+                }
+            }
+            EditorEvent::LoadDefaultPreset(index) => {
+                if let Some(preset) = presets::factory_bank().get(*index) {
+                    let values = preset.normalized_values(&self.tape_data);
+                    self.history.ignore_next_gestures(values.iter().map(|(ptr, _)| *ptr));
+                    set_normalized_values(cx, &values);
+                    self.preset_name = preset.name.clone();
+                }
+            }
+            EditorEvent::SelectSlot(slot) => {
+                let live = ParamSnapshot::capture(&self.tape_data);
+                let target = self.tape_data.ab_slots.lock().unwrap().select(*slot, live);
+                if let Some(target) = target {
+                    set_normalized_values(cx, &target.normalized_values(&self.tape_data));
+                }
+                self.is_slot_b_active = *slot == AbSlot::B;
+            }
+            EditorEvent::CopyAToB => {
+                let live = ParamSnapshot::capture(&self.tape_data);
+                let target = self.tape_data.ab_slots.lock().unwrap().copy_a_to_b(live);
+                if let Some(target) = target {
+                    set_normalized_values(cx, &target.normalized_values(&self.tape_data));
+                }
+            }
+            EditorEvent::SwapSlots => {
+                let live = ParamSnapshot::capture(&self.tape_data);
+                let target = self.tape_data.ab_slots.lock().unwrap().swap(live);
+                if let Some(target) = target {
+                    set_normalized_values(cx, &target.normalized_values(&self.tape_data));
+                }
+            }
+            EditorEvent::Undo => {
+                if let Some(values) = self.history.undo() {
+                    set_normalized_values(cx, &values);
+                }
+            }
+            EditorEvent::Redo => {
+                if let Some(values) = self.history.redo() {
+                    set_normalized_values(cx, &values);
+                }
+            }
+            EditorEvent::StoreMorphSnapshot(is_end) => {
+                let live = ParamSnapshot::capture(&self.tape_data);
+                let mut snapshots = self.tape_data.morph_snapshots.lock().unwrap();
+                if *is_end {
+                    snapshots.end = Some(live);
+                } else {
+                    snapshots.start = Some(live);
+                }
+                self.has_morph_start = snapshots.start.is_some();
+                self.has_morph_end = snapshots.end.is_some();
+                drop(snapshots);
+
+                self.tape_data.morph_revision.fetch_add(1, Ordering::Release);
+            }
+            EditorEvent::Randomize => {
+                let values = self.randomizer.generate(&self.tape_data, &mut rand::thread_rng());
+                set_normalized_values(cx, &values);
+            }
+            EditorEvent::SetRandomizerIntensity(intensity) => {
+                self.randomizer.intensity = intensity.clamp(0.0, 1.0);
+                *self.tape_data.randomizer.lock().unwrap() = self.randomizer.clone();
+            }
+            EditorEvent::ToggleRandomizerLock(index) => {
+                if let Some(entry) = self.randomizer.entries.get_mut(*index) {
+                    entry.locked = !entry.locked;
+                }
+                *self.tape_data.randomizer.lock().unwrap() = self.randomizer.clone();
+            }
+            EditorEvent::SetRandomizerRange(index, is_max, value) => {
+                if let Some(entry) = self.randomizer.entries.get_mut(*index) {
+                    // Keep the range from turning inside out
+                    if *is_max {
+                        entry.max = value.max(entry.min);
+                    } else {
+                        entry.min = value.min(entry.max);
+                    }
+                }
+                *self.tape_data.randomizer.lock().unwrap() = self.randomizer.clone();
+            }
+            EditorEvent::ChangeSettings(new_settings) => {
+                if new_settings.check_for_updates != self.settings.check_for_updates {
+                    self.update_status.set_enabled(new_settings.check_for_updates);
+                }
+                let is_theme_changed = new_settings.theme != self.settings.theme;
+                self.settings = new_settings.clone();
+                settings::store(new_settings.clone());
+
+                if is_theme_changed {
+                    self.skin.select(self.settings.theme.as_deref());
+                    self.reload_skin(cx);
+                }
+            }
+            EditorEvent::SetTransportOptions(options) => {
+                options.store(&self.tape_data);
+                self.transport_options = *options;
+            }
+            EditorEvent::PollSkin => {
+                if self.skin.modified() != self.skin_modified {
+                    self.reload_skin(cx);
+                }
+            }
+            EditorEvent::OpenMidiMenu(ptr) => {
+                let param_map = self.tape_data.param_map();
+                if let Some((param_id, _, _)) = param_map.iter().find(|(_, p, _)| p == ptr) {
+                    self.midi_learn_target = None;
+                    self.midi_menu = Some(MidiMenu {
+                        target: MidiTarget::Param(param_id.clone()),
+                        // SAFETY: The parameters outlive the editor
+                        name: unsafe { ptr.name() }.to_owned(),
+                        position: midi_menu_position(cx),
+                    });
+                }
+            }
+            EditorEvent::OpenTapMenu => {
+                self.midi_learn_target = None;
+                self.midi_menu = Some(MidiMenu {
+                    target: MidiTarget::TapNote,
+                    name: String::from("Tap"),
+                    position: midi_menu_position(cx),
+                });
+            }
+            EditorEvent::CloseMidiMenu => {
+                self.midi_menu = None;
+                self.midi_learn_target = None;
+            }
+            EditorEvent::StartMidiLearn => {
+                self.midi_learn_target = self.midi_menu.as_ref().map(|menu| menu.target.clone());
+                // Don't learn a note that was played before the button was clicked
+                self.tape_data.midi_inbox.take_note();
+            }
+            EditorEvent::ForgetMidiMapping => match self.midi_menu.as_ref().map(|menu| &menu.target) {
+                Some(MidiTarget::Param(param_id)) => {
+                    let mut mappings = self.tape_data.midi_mappings.lock().unwrap();
+                    mappings.forget(param_id);
+                    self.midi_mappings = mappings.clone();
+                }
+                Some(MidiTarget::TapNote) => {
+                    self.tape_data.tap_note.store(midi_learn::NO_NOTE, Ordering::Relaxed);
+                    self.tap_note = None;
+                }
+                None => (),
+            },
+            EditorEvent::Tap => {
+                self.tape_data.tap_count.fetch_add(1, Ordering::Relaxed);
+            }
+            EditorEvent::PollMidi => {
+                // The host may have restored other mappings with the project
+                if let Ok(mappings) = self.tape_data.midi_mappings.try_lock() {
+                    if *mappings != self.midi_mappings {
+                        self.midi_mappings = mappings.clone();
+                    }
+                }
+
+                let is_tap_ignored =
+                    self.tape_data.time_sync.value() && self.host_info.is_host_tempo();
+                if is_tap_ignored != self.is_tap_ignored {
+                    self.is_tap_ignored = is_tap_ignored;
+                }
+
+                let note = self.tape_data.midi_inbox.take_note();
+                if let (Some(note), Some(MidiTarget::TapNote)) = (note, &self.midi_learn_target) {
+                    self.tape_data.tap_note.store(note as i32, Ordering::Relaxed);
+                    self.tap_note = Some(note);
+                    self.midi_learn_target = None;
+                }
+
+                let tapped_delay_ms = self.tape_data.tapped_delay_ms.swap(-1.0, Ordering::Relaxed);
+                if tapped_delay_ms >= 0.0 {
+                    let param = &self.tape_data.delay_time_ms;
+                    set_normalized_values(
+                        cx,
+                        &[(param.as_ptr(), param.preview_normalized(tapped_delay_ms))],
+                    );
+                }
+
+                let ccs: Vec<(u8, f32)> = self.tape_data.midi_inbox.drain().collect();
+                if ccs.is_empty() {
+                    return;
+                }
+
+                let param_map = self.tape_data.param_map();
+                for (cc, value) in ccs {
+                    if let Some(MidiTarget::Param(param_id)) = &self.midi_learn_target {
+                        let mut mappings = self.tape_data.midi_mappings.lock().unwrap();
+                        mappings.learn(param_id, cc);
+                        self.midi_mappings = mappings.clone();
+                        self.midi_learn_target = None;
+                    }
+
+                    // Every CC gets its own gesture, the undo history merges them while the
+                    // control keeps moving
+                    let param = self.midi_mappings.param_id(cc).and_then(|param_id| {
+                        param_map.iter().find(|(id, _, _)| id == param_id)
+                    });
+                    if let Some((_, ptr, _)) = param {
+                        set_normalized_values(cx, &[(*ptr, value)]);
+                    }
+                }
+            }
+        });
+
+        // Every gesture from the knobs, buttons and the events above passes through here on its
+        // way to the host, which makes this the one place where the history needs to be recorded
+        event.map(|param_event, _| match param_event {
+            RawParamEvent::BeginSetParameter(ptr) => self.history.begin_gesture(*ptr),
+            RawParamEvent::SetParameterNormalized(ptr, normalized) => {
+                self.history.set_value(*ptr, *normalized)
+            }
+            // SAFETY: The editor only sends pointers to parameters that outlive it
+            RawParamEvent::ResetParameter(ptr) => self
+                .history
+                .set_value(*ptr, unsafe { ptr.default_normalized_value() }),
+            RawParamEvent::EndSetParameter(ptr) => self.history.end_gesture(*ptr),
+        });
+
+        event.map(|window_event, meta| {
+            if let WindowEvent::KeyDown(code, _) = window_event {
+                let modifiers = *cx.modifiers();
+                if !(modifiers.contains(Modifiers::CTRL) || modifiers.contains(Modifiers::LOGO)) {
+                    return;
+                }
+
+                match code {
+                    Code::KeyZ if modifiers.contains(Modifiers::SHIFT) => {
+                        cx.emit(EditorEvent::Redo);
+                        meta.consume();
+                    }
+                    Code::KeyZ => {
+                        cx.emit(EditorEvent::Undo);
+                        meta.consume();
+                    }
+                    Code::KeyY => {
+                        cx.emit(EditorEvent::Redo);
+                        meta.consume();
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        self.can_undo = self.history.can_undo();
+        self.can_redo = self.history.can_redo();
+    }
+}
 
 pub(crate) fn default_state() -> Arc<ViziaState> {
-    ViziaState::new(|| (1200, 800))
+    ViziaState::new_with_default_scale_factor(|| (1200, 800), settings::current().gui_scale)
 }
 
 pub(crate) fn create(
     tape_data: Arc<TapeParams>,
-    peak_meter_l: Arc<AtomicF32>,
-    peak_meter_r: Arc<AtomicF32>,
+    meter_levels: Arc<MeterLevels>,
+    drive_level: Arc<AtomicF32>,
     editor_state: Arc<ViziaState>,
-    update_available: Arc<AtomicBool>
+    update_status: Arc<UpdateStatus>,
+    tape_overview: Arc<TapeOverview>,
+    host_info: Arc<HostInfo>,
+    spectrum_ring: Arc<SpectrumRing>,
 ) -> Option<Box<dyn Editor>> {
     create_vizia_editor(editor_state, ViziaTheming::Custom, move |cx, _| {
         register_noto_sans_light(cx);
@@ -58,45 +475,150 @@ pub(crate) fn create(
         cx.add_font_mem(&ORBITRON_TTF);
         cx.set_default_font(&[COMFORTAA]);
 
-        match load_from_memory(BG_IMAGE_BYTES) {
-            Ok(img) => cx.load_image("background_image.png", img, ImageRetentionPolicy::Forever),
-            Err(e) => nih_error!("Failed to load image: {}", e),
-        }
-
-        match load_from_memory(INSTA_ICON_BYTES) {
-            Ok(img) => cx.load_image("insta.png", img, ImageRetentionPolicy::Forever),
-            Err(e) => nih_error!("Failed to load image: {}", e),
-        }
-
-        match load_from_memory(SPOTIFY_ICON_BYTES) {
-            Ok(img) => cx.load_image("spotify.png", img, ImageRetentionPolicy::Forever),
-            Err(e) => nih_error!("Failed to load image: {}", e),
+        let current_settings = settings::current();
+        let skin = SkinStyle::new(current_settings.theme.as_deref());
+        for (name, img) in skin.images() {
+            cx.load_image(name, img, ImageRetentionPolicy::Forever);
         }
 
         if let Err(e) = cx.add_stylesheet(include_style!("/src/resource/style.css")) {
             nih_log!("CSS Error: {:?}", e);
         }
+        // The skin goes last so it overrides the embedded styles
+        if let Err(e) = cx.add_stylesheet(skin.clone()) {
+            nih_log!("CSS Error: {:?}", e);
+        }
+
+        let timer = cx.add_timer(SKIN_POLL_INTERVAL, None, |cx, action| {
+            if let TimerAction::Tick(_) = action {
+                cx.emit(EditorEvent::PollSkin);
+            }
+        });
+        cx.start_timer(timer);
+        let timer = cx.add_timer(MIDI_POLL_INTERVAL, None, |cx, action| {
+            if let TimerAction::Tick(_) = action {
+                cx.emit(EditorEvent::PollMidi);
+            }
+        });
+        cx.start_timer(timer);
+
+        let randomizer = {
+            let mut randomizer = tape_data.randomizer.lock().unwrap();
+            randomizer.sync_with(&tape_data);
+            randomizer.clone()
+        };
 
         Data {
             tape_data: tape_data.clone(),
-            peak_meter_l: peak_meter_l.clone(),
-            peak_meter_r: peak_meter_r.clone(),
+            meter_levels: meter_levels.clone(),
+            drive_level: drive_level.clone(),
+            tape_overview: tape_overview.clone(),
+            spectrum_ring: spectrum_ring.clone(),
+            preset_name: String::from("Presets"),
+            is_slot_b_active: tape_data.ab_slots.lock().unwrap().active == AbSlot::B,
+            history: UndoHistory::default(),
+            can_undo: false,
+            can_redo: false,
+            has_morph_start: tape_data.morph_snapshots.lock().unwrap().start.is_some(),
+            has_morph_end: tape_data.morph_snapshots.lock().unwrap().end.is_some(),
+            randomizer,
+            update_status: update_status.clone(),
+            skin_modified: skin.modified(),
+            skin,
+            settings: current_settings,
+            midi_mappings: tape_data.midi_mappings.lock().unwrap().clone(),
+            tap_note: u8::try_from(tape_data.tap_note.load(Ordering::Relaxed)).ok(),
+            midi_menu: None,
+            midi_learn_target: None,
+            transport_options: TransportOptions::load(&tape_data),
+            host_info: host_info.clone(),
+            is_tap_ignored: false,
         }
         .build(cx);
 
-        let update_text = if update_available.load(Ordering::Relaxed) { "New Version available!" } else { "Check for Updates" };
+        // Fresh instances start out with the default preset from the settings. The values go
+        // through the host like any other edit, so this has to wait until the editor opens.
+        if !tape_data.default_preset_applied.swap(true, Ordering::Relaxed) {
+            let default_preset = settings::current().default_preset.and_then(|name| {
+                presets::factory_bank().iter().position(|preset| preset.name == name)
+            });
+            if let Some(index) = default_preset {
+                cx.emit(EditorEvent::LoadDefaultPreset(index));
+            }
+        }
 
         VStack::new(cx, |cx| {
             VStack::new(cx, |cx| {
                 Label::new(cx, "CONVOLUTION'S TAPE DELAY").class("header-title");
                 HStack::new(cx, |cx| {
-                    Label::new(cx, update_text)
-                        .class("update-link")
-                        .on_press(|_| {
-                            if let Err(e) = webbrowser::open("https://github.com/minburg/vst-tape-delay/releases") {
-                                nih_log!("Failed to open browser: {}", e);
-                            }
-                        });
+                    Dropdown::new(
+                        cx,
+                        |cx| Label::new(cx, Data::preset_name).class("preset-menu-title"),
+                        |cx| {
+                            ScrollView::new(cx, 0.0, 0.0, false, true, |cx| {
+                                for (index, preset) in presets::factory_bank().iter().enumerate() {
+                                    VStack::new(cx, |cx| {
+                                        Label::new(cx, preset.name.as_str()).class("preset-name");
+                                        Label::new(cx, &preset.tags.join(" / ")).class("preset-tags");
+                                        Label::new(cx, preset.description.as_str())
+                                            .class("preset-description");
+                                    })
+                                        .class("preset-entry")
+                                        .on_press(move |cx| {
+                                            cx.emit(EditorEvent::LoadPreset(index));
+                                            cx.emit(PopupEvent::Close);
+                                        });
+                                }
+                            })
+                                .height(Pixels(420.0));
+                        },
+                    )
+                        .class("preset-menu");
+                    HStack::new(cx, |cx| {
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(EditorEvent::SelectSlot(AbSlot::A)),
+                            |cx| Label::new(cx, "A"),
+                        )
+                            .class("ab-button")
+                            .toggle_class("active", Data::is_slot_b_active.map(|b| !*b));
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(EditorEvent::SelectSlot(AbSlot::B)),
+                            |cx| Label::new(cx, "B"),
+                        )
+                            .class("ab-button")
+                            .toggle_class("active", Data::is_slot_b_active);
+                        Button::new(cx, |cx| cx.emit(EditorEvent::CopyAToB), |cx| {
+                            Label::new(cx, "A\u{2192}B")
+                        })
+                            .class("ab-action-button");
+                        Button::new(cx, |cx| cx.emit(EditorEvent::SwapSlots), |cx| {
+                            Label::new(cx, "Swap")
+                        })
+                            .class("ab-action-button");
+                    })
+                        .class("ab-section");
+                    create_randomizer_controls(cx, &tape_data);
+                    HStack::new(cx, |cx| {
+                        Button::new(cx, |cx| cx.emit(EditorEvent::Undo), |cx| {
+                            Label::new(cx, "Undo")
+                        })
+                            .class("ab-action-button")
+                            .disabled(Data::can_undo.map(|can_undo| !*can_undo));
+                        Button::new(cx, |cx| cx.emit(EditorEvent::Redo), |cx| {
+                            Label::new(cx, "Redo")
+                        })
+                            .class("ab-action-button")
+                            .disabled(Data::can_redo.map(|can_redo| !*can_redo));
+                    })
+                        .class("ab-section");
+                    create_transport_menu(cx);
+                    create_lfo_menu(cx);
+                    create_envelope_menu(cx);
+                    create_loop_menu(cx);
+                    create_update_menu(cx);
+                    create_settings_menu(cx);
                     Label::new(cx, "v0.1.13").class("header-version-title");
                     Element::new(cx)
                         .class("insta-button")
@@ -120,30 +642,88 @@ pub(crate) fn create(
                 .class("title-section");
 
             HStack::new(cx, |cx| {
-                VStack::new(cx, |cx| {
+                // Input
+                HStack::new(cx, |cx| {
                     MyPeakMeter::new(
                         cx,
-                        Data::peak_meter_l.map(|peak_meter_l| {
-                            util::gain_to_db(peak_meter_l.load(Ordering::Relaxed))
-                        }),
+                        Data::meter_levels.map(|levels| levels.input_l.clone()),
                         Some(Duration::from_millis(30)),
+                        Orientation::Vertical,
                     )
                         .class("vu-meter-no-text")
-                        .width(Stretch(1.0))
-                        .height(Pixels(45.0));
+                        .width(Stretch(0.6))
+                        .height(Stretch(1.0));
                     MyPeakMeter::new(
                         cx,
-                        Data::peak_meter_r.map(|peak_meter_r| {
-                            util::gain_to_db(peak_meter_r.load(Ordering::Relaxed))
-                        }),
+                        Data::meter_levels.map(|levels| levels.input_r.clone()),
                         Some(Duration::from_millis(30)),
+                        Orientation::Vertical,
                     )
                         .class("vu-meter")
                         .width(Stretch(1.0))
-                        .height(Pixels(45.0));
+                        .height(Stretch(1.0));
                 })
                     .height(Stretch(1.0))
-                    .width(Stretch(1.1));
+                    .width(Stretch(0.3))
+                    .class("input-meters");
+
+                // Output, as either bar meters or VU meters
+                Binding::new(
+                    cx,
+                    Data::settings.map(|settings| {
+                        (settings.meter_style, settings.vu_reference_dbfs)
+                    }),
+                    |cx, meter_settings| {
+                        let (meter_style, vu_reference_dbfs) = meter_settings.get(cx);
+                        match meter_style {
+                            MeterStyle::Bar => {
+                                VStack::new(cx, |cx| {
+                                    MyPeakMeter::new(
+                                        cx,
+                                        Data::meter_levels.map(|levels| levels.output_l.clone()),
+                                        Some(Duration::from_millis(30)),
+                                        Orientation::Horizontal,
+                                    )
+                                        .class("vu-meter-no-text")
+                                        .width(Stretch(1.0))
+                                        .height(Pixels(45.0));
+                                    MyPeakMeter::new(
+                                        cx,
+                                        Data::meter_levels.map(|levels| levels.output_r.clone()),
+                                        Some(Duration::from_millis(30)),
+                                        Orientation::Horizontal,
+                                    )
+                                        .class("vu-meter")
+                                        .width(Stretch(1.0))
+                                        .height(Pixels(45.0));
+                                })
+                                    .height(Stretch(1.0))
+                                    .width(Stretch(1.1));
+                            }
+                            MeterStyle::Vu => {
+                                HStack::new(cx, |cx| {
+                                    VuMeter::new(
+                                        cx,
+                                        Data::meter_levels.map(|levels| levels.output_l.clone()),
+                                        vu_reference_dbfs,
+                                    )
+                                        .width(Stretch(1.0))
+                                        .height(Stretch(1.0));
+                                    VuMeter::new(
+                                        cx,
+                                        Data::meter_levels.map(|levels| levels.output_r.clone()),
+                                        vu_reference_dbfs,
+                                    )
+                                        .width(Stretch(1.0))
+                                        .height(Stretch(1.0));
+                                })
+                                    .height(Stretch(1.0))
+                                    .width(Stretch(1.1))
+                                    .class("vu-meters");
+                            }
+                        }
+                    },
+                );
 
                 HStack::new(cx, |cx| {
                     // Broken
@@ -180,6 +760,24 @@ pub(crate) fn create(
                         .child_top(Stretch(0.08))
                         .child_bottom(Stretch(0.08));
 
+                    // Tap tempo, reacts on mouse down so the taps land where they're meant to
+                    HStack::new(cx, |cx| {
+                        Label::new(cx, "Tap").hoverable(false);
+                    })
+                        .class("tap-param-button")
+                        .disabled(Data::is_tap_ignored)
+                        .on_mouse_down(|cx, button| match button {
+                            MouseButton::Left if !cx.is_disabled() => cx.emit(EditorEvent::Tap),
+                            MouseButton::Right => cx.emit(EditorEvent::OpenTapMenu),
+                            _ => (),
+                        })
+                        .width(Stretch(0.3))
+                        .height(Stretch(0.5))
+                        .child_left(Stretch(1.0))
+                        .child_right(Stretch(1.0))
+                        .child_top(Stretch(0.08))
+                        .child_bottom(Stretch(0.08));
+
                     // Tape Only
                     create_text_button(
                         cx,
@@ -209,6 +807,42 @@ pub(crate) fn create(
                 .child_bottom(Stretch(0.01))
                 .class("meter-section");
 
+            HStack::new(cx, |cx| {
+                TapeView::new(cx, Data::tape_overview)
+                    .width(Stretch(1.0))
+                    .height(Stretch(1.0));
+                let echo_host_info = host_info.clone();
+                EchoView::new(
+                    cx,
+                    Data::tape_data.map(move |params| {
+                        echo_timeline::echo_train(
+                            params,
+                            echo_host_info.tempo(),
+                            echo_host_info.sample_rate(),
+                        )
+                    }),
+                )
+                    .width(Stretch(1.0))
+                    .height(Stretch(1.0));
+                SaturationView::new(cx, Data::tape_data, Data::drive_level)
+                    .width(Stretch(0.5))
+                    .height(Stretch(1.0));
+                SpectrumView::new(
+                    cx,
+                    Data::spectrum_ring,
+                    Data::settings.map(|settings| settings.spectrum_smoothing),
+                    Data::settings.map(|settings| settings.spectrum_peak_hold),
+                    host_info.clone(),
+                )
+                    .width(Stretch(1.0))
+                    .height(Stretch(1.0));
+            })
+                .width(Stretch(0.8))
+                .height(Stretch(0.15))
+                .child_top(Stretch(0.05))
+                .child_bottom(Stretch(0.05))
+                .class("display-section");
+
             HStack::new(cx, |cx| {
                 HStack::new(cx, |cx| {
                     SingleKnob::new(cx, Data::tape_data, |params| &params.noise, false)
@@ -219,6 +853,101 @@ pub(crate) fn create(
                         .width(Stretch(1.0));
                 })
                     .class("finetune-section-inner");
+
+                HStack::new(cx, |cx| {
+                    VStack::new(cx, |cx| {
+                        create_text_button(
+                            cx,
+                            "Morph",
+                            Data::tape_data.map(|p| p.morph_active.value()),
+                            &tape_data,
+                            |p| &p.morph_active,
+                            "morph-param-button",
+                            "active",
+                        );
+                        Button::new(cx, |cx| cx.emit(EditorEvent::StoreMorphSnapshot(false)), |cx| {
+                            Label::new(cx, "Set Start")
+                        })
+                            .class("morph-store-button")
+                            .toggle_class("stored", Data::has_morph_start);
+                        Button::new(cx, |cx| cx.emit(EditorEvent::StoreMorphSnapshot(true)), |cx| {
+                            Label::new(cx, "Set End")
+                        })
+                            .class("morph-store-button")
+                            .toggle_class("stored", Data::has_morph_end);
+                    })
+                        .row_between(Pixels(6.0))
+                        .child_space(Stretch(1.0))
+                        .width(Stretch(1.0));
+                    SingleKnob::new(cx, Data::tape_data, |params| &params.morph, false)
+                        .width(Stretch(1.0));
+                    SingleKnob::new(cx, Data::tape_data, |params| &params.morph_switch_point, false)
+                        .width(Stretch(1.0));
+                })
+                    .class("finetune-section-inner");
+
+                HStack::new(cx, |cx| {
+                    VStack::new(cx, |cx| {
+                        create_text_button(
+                            cx,
+                            "Resonator",
+                            Data::tape_data.map(|p| p.resonator.value()),
+                            &tape_data,
+                            |p| &p.resonator,
+                            "resonator-param-button",
+                            "active",
+                        );
+                    })
+                        .child_space(Stretch(1.0))
+                        .width(Stretch(1.0));
+                    SingleKnob::new(cx, Data::tape_data, |params| &params.glide_ms, false)
+                        .width(Stretch(1.0));
+                })
+                    .class("finetune-section-inner");
+
+                HStack::new(cx, |cx| {
+                    VStack::new(cx, |cx| {
+                        create_text_button(
+                            cx,
+                            "L/R Split",
+                            Data::tape_data.map(|p| p.split_divisions.value()),
+                            &tape_data,
+                            |p| &p.split_divisions,
+                            "division-param-button",
+                            "active",
+                        );
+                        create_text_button(
+                            cx,
+                            "Custom",
+                            Data::tape_data.map(|p| p.custom_division.value()),
+                            &tape_data,
+                            |p| &p.custom_division,
+                            "division-param-button",
+                            "active",
+                        );
+                    })
+                        .row_between(Pixels(6.0))
+                        .child_space(Stretch(1.0))
+                        .width(Stretch(1.0));
+                    SingleKnob::new(cx, Data::tape_data, |params| &params.swing, false)
+                        .width(Stretch(1.0));
+                    // The division knobs only show up while their mode is on
+                    Binding::new(cx, Data::tape_data.map(|p| p.split_divisions.value()), |cx, is_split| {
+                        if is_split.get(cx) {
+                            SingleKnob::new(cx, Data::tape_data, |params| &params.division_right, false)
+                                .width(Stretch(1.0));
+                        }
+                    });
+                    Binding::new(cx, Data::tape_data.map(|p| p.custom_division.value()), |cx, is_custom| {
+                        if is_custom.get(cx) {
+                            SingleKnob::new(cx, Data::tape_data, |params| &params.custom_numerator, false)
+                                .width(Stretch(1.0));
+                            SingleKnob::new(cx, Data::tape_data, |params| &params.custom_denominator, false)
+                                .width(Stretch(1.0));
+                        }
+                    });
+                })
+                    .class("finetune-section-inner");
             })
                 .width(Stretch(1.0))
                 .height(Stretch(0.4))
@@ -247,10 +976,16 @@ pub(crate) fn create(
                                 .class("portion"); // Apply same CSS class for consistent layout
                         } else {
                             // --- MODE OFF: SHOW REAL KNOB ---
-                            // This is your original knob bound to 'mix'.
-                            // It remembers its position (e.g. 30%).
-                            ParamKnob::new(cx, Data::tape_data, |params| &params.delay_time_ms, false)
-                                .width(Stretch(1.0));
+                            // Time Sync swaps the free delay time for the note division
+                            Binding::new(cx, Data::tape_data.map(|p| p.time_sync.value()), |cx, is_sync_lens| {
+                                if is_sync_lens.get(cx) {
+                                    ParamKnob::new(cx, Data::tape_data, |params| &params.division, false)
+                                        .width(Stretch(1.0));
+                                } else {
+                                    ParamKnob::new(cx, Data::tape_data, |params| &params.delay_time_ms, false)
+                                        .width(Stretch(1.0));
+                                }
+                            });
                         }
                     });
                 })
@@ -311,7 +1046,697 @@ pub(crate) fn create(
             .class("main-gui");
 
         ResizeHandle::new(cx);
+        create_midi_menu(cx);
+    })
+}
+
+/// The MIDI learn menu. It opens where a knob or a toggle was right-clicked, and clicking anywhere
+/// outside of it closes it again.
+fn create_midi_menu(cx: &mut Context) {
+    Binding::new(cx, Data::midi_menu, |cx, menu| {
+        let Some(menu) = menu.get(cx) else {
+            return;
+        };
+
+        Element::new(cx)
+            .class("midi-menu-backdrop")
+            .position_type(PositionType::SelfDirected)
+            .width(Stretch(1.0))
+            .height(Stretch(1.0))
+            .on_press(|cx| cx.emit(EditorEvent::CloseMidiMenu));
+
+        VStack::new(cx, |cx| {
+            Label::new(cx, &menu.name).class("release-title");
+
+            match &menu.target {
+                MidiTarget::Param(param_id) => {
+                    let param_id = param_id.clone();
+                    let assigned_cc =
+                        Data::midi_mappings.map(move |mappings| mappings.cc(&param_id));
+                    create_midi_menu_controls(
+                        cx,
+                        assigned_cc.map(|cc| match cc {
+                            Some(cc) => format!("Controlled by CC {cc}"),
+                            None => String::from("No CC assigned"),
+                        }),
+                        assigned_cc.map(|cc| cc.is_some()),
+                        "Move a control on your MIDI device...",
+                    );
+                }
+                MidiTarget::TapNote => {
+                    create_midi_menu_controls(
+                        cx,
+                        Data::tap_note.map(|note| match note {
+                            Some(note) => format!("Tapped by note {}", note_name(*note)),
+                            None => String::from("No note assigned"),
+                        }),
+                        Data::tap_note.map(|note| note.is_some()),
+                        "Play a note on your MIDI device...",
+                    );
+                }
+            }
+        })
+            .class("midi-menu")
+            .position_type(PositionType::SelfDirected)
+            .left(Pixels(menu.position.0))
+            .top(Pixels(menu.position.1))
+            .width(Pixels(MIDI_MENU_WIDTH));
+    });
+}
+
+/// The MIDI learn menu's description of the current assignment, its prompt while waiting for
+/// MIDI, and its buttons.
+fn create_midi_menu_controls<S, A>(cx: &mut Context, status: S, is_assigned: A, learn_prompt: &str)
+where
+    S: Lens<Target = String>,
+    A: Lens<Target = bool>,
+{
+    let is_learning = Data::midi_learn_target.map(|target| target.is_some());
+    Label::new(cx, status)
+        .class("preset-description")
+        .display(is_learning.map(|is_learning| !*is_learning));
+    Label::new(cx, learn_prompt)
+        .class("preset-description")
+        .class("midi-menu-learning")
+        .display(is_learning);
+
+    HStack::new(cx, |cx| {
+        Button::new(cx, |cx| cx.emit(EditorEvent::StartMidiLearn), |cx| {
+            Label::new(cx, "MIDI Learn")
+        })
+            .class("ab-action-button")
+            .toggle_class("active", is_learning);
+        Button::new(cx, |cx| cx.emit(EditorEvent::ForgetMidiMapping), |cx| {
+            Label::new(cx, "Forget")
+        })
+            .class("ab-action-button")
+            .disabled(is_assigned.map(|is_assigned| !*is_assigned));
+    })
+        .class("midi-menu-buttons");
+}
+
+/// Where the MIDI learn menu opens: at the mouse cursor, but kept inside the window when opening
+/// near the right edge. In logical pixels.
+fn midi_menu_position(cx: &mut EventContext) -> (f32, f32) {
+    let window = cx.bounds();
+    let x = cx.mouse().cursorx.min(window.right() - cx.logical_to_physical(MIDI_MENU_WIDTH));
+    (
+        cx.physical_to_logical(x - window.x),
+        cx.physical_to_logical(cx.mouse().cursory - window.y),
+    )
+}
+
+/// A MIDI note number as a note name, with middle C (60) as C4.
+fn note_name(note: u8) -> String {
+    format!("{}{}", nih_plug::util::NOTES[note as usize % 12], note as i32 / 12 - 1)
+}
+
+/// The options for following the host's transport. Unlike the Settings, these are stored with the
+/// project.
+fn create_transport_menu(cx: &mut Context) {
+    Dropdown::new(
+        cx,
+        |cx| Label::new(cx, "Transport").class("update-link"),
+        |cx| {
+            VStack::new(cx, |cx| {
+                create_transport_option(
+                    cx,
+                    "Clear the tape when playback starts or loops",
+                    |options| &mut options.clear_on_play,
+                );
+                create_transport_option(
+                    cx,
+                    "Keep tails when playback stops",
+                    |options| &mut options.keep_tails_on_stop,
+                );
+                create_transport_option(
+                    cx,
+                    "Lock the flutter to the song position",
+                    |options| &mut options.phase_lock,
+                );
+            })
+                .class("update-menu-content");
+        },
+    )
+        .class("update-menu")
+        .class("transport-menu");
+}
+
+/// A checkbox for one of the transport options. `option` selects the option's field.
+fn create_transport_option(
+    cx: &mut Context,
+    name: &'static str,
+    option: fn(&mut TransportOptions) -> &mut bool,
+) {
+    HStack::new(cx, |cx| {
+        Checkbox::new(
+            cx,
+            Data::transport_options.map(move |options| {
+                let mut options = *options;
+                *option(&mut options)
+            }),
+        )
+            .on_toggle(move |cx| {
+                if let Some(data) = cx.data::<Data>() {
+                    let mut options = data.transport_options;
+                    *option(&mut options) = !*option(&mut options);
+                    cx.emit(EditorEvent::SetTransportOptions(options));
+                }
+            });
+        Label::new(cx, name).class("preset-description");
+    })
+        .class("randomizer-row");
+}
+
+/// The LFOs and their routing matrix. Every row is a setting or a modulation target, with a column
+/// per LFO.
+fn create_lfo_menu(cx: &mut Context) {
+    Dropdown::new(
+        cx,
+        |cx| Label::new(cx, "LFO").class("update-link"),
+        |cx| {
+            VStack::new(cx, |cx| {
+                HStack::new(cx, |cx| {
+                    Label::new(cx, "").class("randomizer-name");
+                    for number in 1..=NUM_LFOS {
+                        Label::new(cx, &format!("LFO {number}")).class("lfo-header");
+                    }
+                })
+                    .class("randomizer-row");
+
+                let stepped = ParamSliderStyle::CurrentStepLabeled { even: true };
+                create_lfo_row(cx, "Shape", |lfo| &lfo.shape, stepped);
+                create_lfo_row(cx, "Sync", |lfo| &lfo.sync, stepped);
+                create_lfo_row(cx, "Rate", |lfo| &lfo.rate, ParamSliderStyle::FromLeft);
+                create_lfo_row(cx, "Division", |lfo| &lfo.division, ParamSliderStyle::CurrentStep {
+                    even: true,
+                });
+                create_lfo_row(cx, "L/R Phase", |lfo| &lfo.stereo_phase, ParamSliderStyle::FromLeft);
+
+                Label::new(cx, "Depth").class("randomizer-header-wide");
+                for target in LfoTarget::ALL {
+                    create_lfo_row(
+                        cx,
+                        target.name(),
+                        move |lfo| lfo.depth(target),
+                        ParamSliderStyle::Centered,
+                    );
+                }
+            })
+                .class("randomizer-menu-content");
+        },
+    )
+        .class("update-menu")
+        .class("lfo-menu");
+}
+
+/// A row of the LFO menu with a slider for every LFO. `param` selects the LFO's parameter.
+fn create_lfo_row<P, F>(cx: &mut Context, name: &str, param: F, style: ParamSliderStyle)
+where
+    P: Param + 'static,
+    F: Fn(&LfoParams) -> &P + Copy + 'static,
+{
+    HStack::new(cx, |cx| {
+        Label::new(cx, name).class("randomizer-name");
+        for index in 0..NUM_LFOS {
+            ParamSlider::new(cx, Data::tape_data, move |tape_data| param(&tape_data.lfos[index]))
+                .set_style(style)
+                .class("lfo-slider");
+        }
+    })
+        .class("randomizer-row");
+}
+
+/// The envelope follower's settings and its depth for every target it can modulate.
+fn create_envelope_menu(cx: &mut Context) {
+    Dropdown::new(
+        cx,
+        |cx| Label::new(cx, "Envelope").class("update-link"),
+        |cx| {
+            VStack::new(cx, |cx| {
+                create_param_row(cx, "Attack", |p| &p.envelope.attack_ms, ParamSliderStyle::FromLeft);
+                create_param_row(cx, "Release", |p| &p.envelope.release_ms, ParamSliderStyle::FromLeft);
+                create_param_row(
+                    cx,
+                    "Sensitivity",
+                    |p| &p.envelope.sensitivity_db,
+                    ParamSliderStyle::FromLeft,
+                );
+
+                Label::new(cx, "Depth").class("randomizer-header-wide");
+                for (index, target) in ENVELOPE_TARGETS.into_iter().enumerate() {
+                    create_param_row(
+                        cx,
+                        target.name(),
+                        move |p| p.envelope.depths()[index],
+                        ParamSliderStyle::Centered,
+                    );
+                }
+            })
+                .class("randomizer-menu-content");
+        },
+    )
+        .class("update-menu")
+        .class("slider-menu");
+}
+
+/// The pitch shifter and the diffusion network in the feedback loop.
+fn create_loop_menu(cx: &mut Context) {
+    Dropdown::new(
+        cx,
+        |cx| Label::new(cx, "Loop").class("update-link"),
+        |cx| {
+            VStack::new(cx, |cx| {
+                Label::new(cx, "Pitch Shift").class("randomizer-header-wide");
+                create_param_row(cx, "Pitch", |p| &p.pitch_semitones, ParamSliderStyle::Centered);
+                create_param_row(cx, "Fine", |p| &p.pitch_cents, ParamSliderStyle::Centered);
+                create_param_row(cx, "Shift Mix", |p| &p.pitch_mix, ParamSliderStyle::FromLeft);
+                create_param_row(cx, "Grain", |p| &p.pitch_grain_ms, ParamSliderStyle::FromLeft);
+
+                Label::new(cx, "Diffusion").class("randomizer-header-wide");
+                create_param_row(cx, "Amount", |p| &p.diffusion, ParamSliderStyle::FromLeft);
+                create_param_row(cx, "Size", |p| &p.diffusion_size, ParamSliderStyle::FromLeft);
+                create_param_row(cx, "Modulation", |p| &p.diffusion_modulation, ParamSliderStyle::FromLeft);
+            })
+                .class("randomizer-menu-content");
+        },
+    )
+        .class("update-menu")
+        .class("slider-menu");
+}
+
+/// A row with a single slider, for the menus that aren't a matrix.
+fn create_param_row<P, F>(cx: &mut Context, name: &str, param: F, style: ParamSliderStyle)
+where
+    P: Param + 'static,
+    F: Fn(&TapeParams) -> &P + Copy + 'static,
+{
+    HStack::new(cx, |cx| {
+        Label::new(cx, name).class("randomizer-name");
+        ParamSlider::new(cx, Data::tape_data, move |tape_data| param(tape_data))
+            .set_style(style)
+            .class("lfo-slider");
     })
+        .class("randomizer-row");
+}
+
+/// The update notification. Opening it shows the release notes for the new version, a link to the
+/// download page, and the opt-out for the update check.
+fn create_update_menu(cx: &mut Context) {
+    Dropdown::new(
+        cx,
+        |cx| {
+            Label::new(
+                cx,
+                Data::update_status.map(|status| {
+                    if status.is_update_available() {
+                        "New Version available!"
+                    } else {
+                        "Check for Updates"
+                    }
+                }),
+            )
+                .class("update-link")
+        },
+        |cx| {
+            VStack::new(cx, |cx| {
+                Label::new(
+                    cx,
+                    Data::update_status.map(|status| match status.latest_release() {
+                        Some(release) => release.name.unwrap_or(release.tag_name),
+                        None => String::from("You are running the latest version."),
+                    }),
+                )
+                    .class("release-title");
+                ScrollView::new(cx, 0.0, 0.0, false, true, |cx| {
+                    Label::new(
+                        cx,
+                        Data::update_status.map(|status| {
+                            status
+                                .latest_release()
+                                .and_then(|release| release.body)
+                                .unwrap_or_default()
+                        }),
+                    )
+                        .class("release-notes");
+                })
+                    .class("release-notes-scroll")
+                    .display(Data::update_status.map(|status| status.is_update_available()));
+                Button::new(
+                    cx,
+                    |cx| {
+                        let url = cx
+                            .data::<Data>()
+                            .and_then(|data| data.update_status.latest_release())
+                            .and_then(|release| release.html_url)
+                            .unwrap_or_else(|| String::from(RELEASES_URL));
+                        if let Err(e) = webbrowser::open(&url) {
+                            nih_log!("Failed to open browser: {}", e);
+                        }
+                    },
+                    |cx| Label::new(cx, "Open Download Page"),
+                )
+                    .class("ab-action-button")
+                    .class("release-download-button");
+                HStack::new(cx, |cx| {
+                    Checkbox::new(cx, Data::settings.map(|settings| settings.check_for_updates))
+                        .on_toggle(|cx| {
+                            change_settings(cx, |settings| {
+                                settings.check_for_updates = !settings.check_for_updates
+                            })
+                        });
+                    Label::new(cx, "Check for updates daily").class("preset-description");
+                })
+                    .class("randomizer-row");
+            })
+                .class("update-menu-content");
+        },
+    )
+        .class("update-menu");
+}
+
+/// The Settings page for the user's preferences. These apply to every instance of the plugin, not
+/// just the current project.
+fn create_settings_menu(cx: &mut Context) {
+    Dropdown::new(
+        cx,
+        |cx| Label::new(cx, "Settings").class("update-link"),
+        |cx| {
+            VStack::new(cx, |cx| {
+                HStack::new(cx, |cx| {
+                    Label::new(cx, "Window size").class("settings-name");
+                    for &scale in settings::GUI_SCALES {
+                        Button::new(
+                            cx,
+                            move |cx| change_settings(cx, |settings| settings.gui_scale = scale),
+                            move |cx| {
+                                let text = format!("{:.0}%", scale * 100.0);
+                                Label::new(cx, &text)
+                            },
+                        )
+                            .class("settings-choice")
+                            .toggle_class(
+                                "active",
+                                Data::settings.map(move |settings| settings.gui_scale == scale),
+                            );
+                    }
+                })
+                    .class("randomizer-row");
+                HStack::new(cx, |cx| {
+                    Label::new(cx, "Meter release").class("settings-name");
+                    for &(name, rate) in settings::METER_RELEASE_RATES {
+                        Button::new(
+                            cx,
+                            move |cx| {
+                                change_settings(cx, |settings| {
+                                    settings.meter_release_db_per_second = rate
+                                })
+                            },
+                            move |cx| Label::new(cx, name),
+                        )
+                            .class("settings-choice")
+                            .toggle_class(
+                                "active",
+                                Data::settings.map(move |settings| {
+                                    settings.meter_release_db_per_second == rate
+                                }),
+                            );
+                    }
+                })
+                    .class("randomizer-row");
+                HStack::new(cx, |cx| {
+                    Label::new(cx, "Spectrum smoothing").class("settings-name");
+                    Slider::new(cx, Data::settings.map(|settings| settings.spectrum_smoothing))
+                        .on_changing(|cx, value| {
+                            change_settings(cx, |settings| settings.spectrum_smoothing = value)
+                        })
+                        .class("randomizer-slider");
+                })
+                    .class("randomizer-row");
+                HStack::new(cx, |cx| {
+                    Checkbox::new(cx, Data::settings.map(|settings| settings.spectrum_peak_hold))
+                        .on_toggle(|cx| {
+                            change_settings(cx, |settings| {
+                                settings.spectrum_peak_hold = !settings.spectrum_peak_hold
+                            })
+                        });
+                    Label::new(cx, "Hold spectrum peaks").class("preset-description");
+                })
+                    .class("randomizer-row");
+                HStack::new(cx, |cx| {
+                    Label::new(cx, "Output meters").class("settings-name");
+                    for (name, style) in [("Bar", MeterStyle::Bar), ("VU", MeterStyle::Vu)] {
+                        Button::new(
+                            cx,
+                            move |cx| change_settings(cx, |settings| settings.meter_style = style),
+                            move |cx| Label::new(cx, name),
+                        )
+                            .class("settings-choice")
+                            .toggle_class(
+                                "active",
+                                Data::settings.map(move |settings| settings.meter_style == style),
+                            );
+                    }
+                })
+                    .class("randomizer-row");
+                HStack::new(cx, |cx| {
+                    Label::new(cx, "0 VU level").class("settings-name");
+                    for &level in settings::VU_REFERENCE_LEVELS {
+                        Button::new(
+                            cx,
+                            move |cx| {
+                                change_settings(cx, |settings| settings.vu_reference_dbfs = level)
+                            },
+                            move |cx| {
+                                let text = format!("{level:.0}");
+                                Label::new(cx, &text)
+                            },
+                        )
+                            .class("settings-choice")
+                            .toggle_class(
+                                "active",
+                                Data::settings.map(move |settings| {
+                                    settings.vu_reference_dbfs == level
+                                }),
+                            );
+                    }
+                })
+                    .class("randomizer-row");
+                HStack::new(cx, |cx| {
+                    Label::new(cx, "Default preset").class("settings-name");
+                    Button::new(
+                        cx,
+                        |cx| {
+                            change_settings(cx, |settings| {
+                                let names: Vec<Option<String>> = std::iter::once(None)
+                                    .chain(
+                                        presets::factory_bank()
+                                            .iter()
+                                            .map(|preset| Some(preset.name.clone())),
+                                    )
+                                    .collect();
+                                settings.default_preset = next_choice(&names, &settings.default_preset);
+                            })
+                        },
+                        |cx| {
+                            Label::new(
+                                cx,
+                                Data::settings.map(|settings| {
+                                    settings
+                                        .default_preset
+                                        .clone()
+                                        .unwrap_or_else(|| String::from("None"))
+                                }),
+                            )
+                        },
+                    )
+                        .class("settings-cycle");
+                })
+                    .class("randomizer-row");
+                HStack::new(cx, |cx| {
+                    Label::new(cx, "Theme").class("settings-name");
+                    Button::new(
+                        cx,
+                        |cx| {
+                            change_settings(cx, |settings| {
+                                settings.theme = next_choice(&available_themes(), &settings.theme);
+                            })
+                        },
+                        |cx| {
+                            Label::new(
+                                cx,
+                                Data::settings.map(|settings| {
+                                    settings.theme.clone().unwrap_or_else(|| String::from("Default"))
+                                }),
+                            )
+                        },
+                    )
+                        .class("settings-cycle");
+                })
+                    .class("randomizer-row");
+                HStack::new(cx, |cx| {
+                    Checkbox::new(cx, Data::settings.map(|settings| settings.check_for_updates))
+                        .on_toggle(|cx| {
+                            change_settings(cx, |settings| {
+                                settings.check_for_updates = !settings.check_for_updates
+                            })
+                        });
+                    Label::new(cx, "Check for updates daily").class("preset-description");
+                })
+                    .class("randomizer-row");
+                Label::new(
+                    cx,
+                    "The window size applies to newly opened projects. New instances load the default preset the first time their editor opens, so instances that are never opened keep the plugin's defaults.",
+                )
+                    .class("settings-note");
+            })
+                .class("update-menu-content");
+        },
+    )
+        .class("update-menu")
+        .class("settings-menu");
+}
+
+/// The themes the Settings page offers. `None` is the built-in look, everything else is a skin
+/// from the skins folder.
+fn available_themes() -> Vec<Option<String>> {
+    std::iter::once(None)
+        .chain(skin::available_skins().into_iter().map(Some))
+        .collect()
+}
+
+/// Returns the entry after `current` in `choices`, wrapping around at the end.
+fn next_choice(choices: &[Option<String>], current: &Option<String>) -> Option<String> {
+    let index = choices.iter().position(|choice| choice == current);
+    match index {
+        Some(index) => choices[(index + 1) % choices.len()].clone(),
+        None => choices.first().cloned().flatten(),
+    }
+}
+
+/// Applies `change` to a copy of the current settings and stores the result.
+fn change_settings(cx: &mut EventContext, change: impl FnOnce(&mut Settings)) {
+    let Some(mut settings) = cx.data::<Data>().map(|data| data.settings.clone()) else {
+        return;
+    };
+
+    change(&mut settings);
+    cx.emit(EditorEvent::ChangeSettings(settings));
+}
+
+/// The Randomize button, along with a menu for the intensity and the per-parameter locks and
+/// ranges.
+fn create_randomizer_controls(cx: &mut Context, tape_data: &Arc<TapeParams>) {
+    // The display names for the randomizer's entries, in the same order
+    let names: Vec<(String, bool)> = {
+        let param_map = tape_data.param_map();
+        tape_data
+            .randomizer
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|entry| {
+                param_map
+                    .iter()
+                    .find(|(id, _, _)| *id == entry.id)
+                    // SAFETY: The parameters outlive the editor
+                    .map(|(_, ptr, _)| unsafe {
+                        (ptr.name().to_owned(), ptr.step_count() == Some(1))
+                    })
+                    .unwrap_or_else(|| (entry.id.clone(), false))
+            })
+            .collect()
+    };
+
+    HStack::new(cx, |cx| {
+        Button::new(cx, |cx| cx.emit(EditorEvent::Randomize), |cx| {
+            Label::new(cx, "Randomize")
+        })
+            .class("randomize-button");
+        Dropdown::new(
+            cx,
+            |cx| Label::new(cx, "\u{2699}").class("randomizer-menu-title"),
+            move |cx| {
+                VStack::new(cx, |cx| {
+                    HStack::new(cx, |cx| {
+                        Label::new(cx, "Intensity").class("randomizer-name");
+                        Slider::new(cx, Data::randomizer.map(|r| r.intensity))
+                            .on_changing(|cx, value| {
+                                cx.emit(EditorEvent::SetRandomizerIntensity(value))
+                            })
+                            .class("randomizer-slider");
+                    })
+                        .class("randomizer-row");
+                    HStack::new(cx, |cx| {
+                        Label::new(cx, "Lock").class("randomizer-header");
+                        Label::new(cx, "Parameter").class("randomizer-name");
+                        Label::new(cx, "Min").class("randomizer-header-wide");
+                        Label::new(cx, "Max").class("randomizer-header-wide");
+                    })
+                        .class("randomizer-row");
+                    for (index, (name, is_toggle)) in names.iter().enumerate() {
+                        HStack::new(cx, |cx| {
+                            Checkbox::new(
+                                cx,
+                                Data::randomizer.map(move |r| r.entries[index].locked),
+                            )
+                                .on_toggle(move |cx| {
+                                    cx.emit(EditorEvent::ToggleRandomizerLock(index))
+                                })
+                                .class("randomizer-lock");
+                            Label::new(cx, name).class("randomizer-name");
+                            // Toggles can only flip, so they only get a lock
+                            if !is_toggle {
+                                Slider::new(cx, Data::randomizer.map(move |r| r.entries[index].min))
+                                    .on_changing(move |cx, value| {
+                                        cx.emit(EditorEvent::SetRandomizerRange(index, false, value))
+                                    })
+                                    .class("randomizer-slider");
+                                Slider::new(cx, Data::randomizer.map(move |r| r.entries[index].max))
+                                    .on_changing(move |cx, value| {
+                                        cx.emit(EditorEvent::SetRandomizerRange(index, true, value))
+                                    })
+                                    .class("randomizer-slider");
+                            }
+                        })
+                            .class("randomizer-row");
+                    }
+                })
+                    .class("randomizer-menu-content");
+            },
+        )
+            .class("randomizer-menu");
+    })
+        .class("ab-section");
+}
+
+impl Data {
+    /// Reloads the styles and images after the skin or one of its files changed.
+    fn reload_skin(&mut self, cx: &mut EventContext) {
+        self.skin_modified = self.skin.modified();
+        for (name, img) in self.skin.images() {
+            cx.load_image(name, img, ImageRetentionPolicy::Forever);
+        }
+        if let Err(e) = cx.reload_styles() {
+            nih_log!("CSS Error: {:?}", e);
+        }
+    }
+}
+
+/// Sets several parameters at once, e.g. when loading a preset. Every parameter gets its own
+/// gesture, but all gestures are opened before the first value changes so the host sees the whole
+/// change as a single edit.
+fn set_normalized_values(cx: &mut EventContext, values: &[(ParamPtr, f32)]) {
+    for (ptr, _) in values {
+        cx.emit(RawParamEvent::BeginSetParameter(*ptr));
+    }
+    for (ptr, normalized) in values {
+        cx.emit(RawParamEvent::SetParameterNormalized(*ptr, *normalized));
+    }
+    for (ptr, _) in values {
+        cx.emit(RawParamEvent::EndSetParameter(*ptr));
+    }
 }
 
 pub fn create_text_button<'a, T, L, F>(
@@ -330,6 +1755,21 @@ where
 {
     let params_arc = params.clone();
     let selector = selector.clone();
+    let param_ptr = selector(params).as_ptr();
+    let param_static: &'static BoolParam = unsafe { std::mem::transmute(selector(params)) };
+
+    // The timer that closes the gesture 20ms after a press, giving Cubase time to "see" the
+    // change. Every press restarts the same timer.
+    let duration = std::time::Duration::from_millis(20);
+    let timer = cx.add_timer(duration, Some(duration), move |cx, action| {
+        match action {
+            TimerAction::Stop => {
+                cx.emit(ParamEvent::EndSetParameter(param_static));
+                cx.emit(RawParamEvent::EndSetParameter(param_ptr));
+            }
+            _ => {} // Ignore Start and Tick
+        }
+    });
 
     DebugWrapper::new(cx, label_text, move |cx| {
         Label::new(cx, label_text).hoverable(false);
@@ -337,40 +1777,31 @@ where
         .class(class)
         .toggle_class(toggle_class, lens)
         .focusable(true)
+        .on_mouse_down(move |cx, button| {
+            if button == MouseButton::Right {
+                cx.emit(EditorEvent::OpenMidiMenu(param_ptr));
+            }
+        })
         .on_press(move |cx| {
             cx.focus();
             cx.set_active(true);
 
+            // Close the previous press's gesture first if it's still open
+            cx.stop_timer(timer);
+
             let param = selector(&params_arc);
-            let ptr = param.as_ptr();
             let new_value = !param.value();
             let normalized = if new_value { 1.0 } else { 0.0 };
-            let param_static: &'static BoolParam = unsafe { std::mem::transmute(param) };
 
             // --- PHASE 1: OPEN THE GESTURE & PERFORM ---
             cx.emit(ParamEvent::BeginSetParameter(param_static));
-            cx.emit(RawParamEvent::BeginSetParameter(ptr));
+            cx.emit(RawParamEvent::BeginSetParameter(param_ptr));
 
             cx.emit(ParamEvent::SetParameterNormalized(param_static, normalized));
-            cx.emit(RawParamEvent::SetParameterNormalized(ptr, normalized));
+            cx.emit(RawParamEvent::SetParameterNormalized(param_ptr, normalized));
 
             // --- PHASE 2: START THE TIMER TO CLOSE THE GESTURE ---
-            // We set the interval and duration to 20ms.
-            let duration = std::time::Duration::from_millis(20);
-
-            cx.add_timer(duration, Some(duration), move |cx, action| {
-                match action {
-                    TimerAction::Stop => {
-                        // This happens 20ms later, giving Cubase time to "see" the change
-                        cx.emit(ParamEvent::EndSetParameter(param_static));
-                        cx.emit(RawParamEvent::EndSetParameter(ptr));
-                        nih_log!("TIMER: Gesture Closed for {}", label_text);
-                    }
-                    _ => {} // Ignore Start and Tick
-                }
-            });
-
-            nih_log!("TIMER: Gesture Opened for {} (20ms duration)", label_text);
+            cx.start_timer(timer);
         })
 }
 
@@ -0,0 +1,134 @@
+//! The echo timeline: one bar per repeat at its time position, as tall as its level.
+
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+
+use crate::echo_timeline::{EchoTrain, CEILING_DB, FLOOR_DB};
+
+/// The width of a single echo's bar.
+const BAR_WIDTH: f32 = 3.0;
+/// Grid lines closer together than this are left out.
+const MIN_GRID_SPACING: f32 = 4.0;
+
+impl Data for EchoTrain {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+/// The bars use the font color and the grid uses the border color. With Time Sync on, the grid
+/// follows the selected note division with stronger lines on every beat. Otherwise there's a line
+/// every 100 ms, or every second for longer echo trains.
+pub struct EchoView<L>
+where
+    L: Lens<Target = EchoTrain>,
+{
+    echo_train: L,
+}
+
+impl<L> EchoView<L>
+where
+    L: Lens<Target = EchoTrain>,
+{
+    pub fn new(cx: &mut Context, echo_train: L) -> Handle<Self> {
+        Self { echo_train }
+            .build(cx, |_| {})
+            .bind(echo_train, |mut handle, _| handle.needs_redraw())
+    }
+}
+
+impl<L> View for EchoView<L>
+where
+    L: Lens<Target = EchoTrain>,
+{
+    fn element(&self) -> Option<&'static str> {
+        Some("echo-view")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let opacity = cx.opacity();
+        let color = |color: Color| {
+            let mut color: vg::Color = color.into();
+            color.set_alphaf(color.a * opacity);
+            color
+        };
+        let background_color = color(cx.background_color());
+        let bar_color = color(cx.font_color());
+        let grid_color = color(cx.border_color());
+        let dpi_scale = cx.logical_to_physical(1.0).max(1.0);
+
+        let mut path = vg::Path::new();
+        path.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&path, &vg::Paint::color(background_color));
+
+        let echo_train = self.echo_train.get(cx);
+        if echo_train.window_seconds <= 0.0 {
+            return;
+        }
+        let time_to_x = |time: f32| bounds.x + (time / echo_train.window_seconds) * bounds.w;
+        let db_to_y = |db: f32| {
+            let fraction = (db.clamp(FLOOR_DB, CEILING_DB) - FLOOR_DB) / (CEILING_DB - FLOOR_DB);
+            bounds.bottom() - fraction * bounds.h
+        };
+
+        // Grid lines, with the thin lines first so the strong ones end up on top
+        let (fine_step, strong_step) = match echo_train.grid {
+            Some(grid) => (grid.division_seconds, Some(grid.beat_seconds)),
+            None if echo_train.window_seconds <= 2.0 => (0.1, None),
+            None => (1.0, None),
+        };
+        let mut stroke_grid = |step: f32, line_width: f32| {
+            if step <= 0.0 || (step / echo_train.window_seconds) * bounds.w < MIN_GRID_SPACING {
+                return;
+            }
+
+            let mut path = vg::Path::new();
+            let mut time = step;
+            while time < echo_train.window_seconds {
+                let x = time_to_x(time);
+                path.move_to(x, bounds.top());
+                path.line_to(x, bounds.bottom());
+                time += step;
+            }
+            let mut paint = vg::Paint::color(grid_color);
+            paint.set_line_width(line_width * dpi_scale);
+            canvas.stroke_path(&path, &paint);
+        };
+        stroke_grid(fine_step, 0.5);
+        if let Some(strong_step) = strong_step {
+            stroke_grid(strong_step, 1.5);
+        }
+
+        // The input's level, for reference
+        let mut path = vg::Path::new();
+        path.move_to(bounds.left(), db_to_y(0.0));
+        path.line_to(bounds.right(), db_to_y(0.0));
+        let mut paint = vg::Paint::color(grid_color);
+        paint.set_line_width(dpi_scale);
+        canvas.stroke_path(&path, &paint);
+
+        let mut bars = vg::Path::new();
+        let mut envelope = vg::Path::new();
+        for (index, &(time, level_db)) in echo_train.echoes.iter().enumerate() {
+            let x = time_to_x(time);
+            let y = db_to_y(level_db);
+            bars.move_to(x, bounds.bottom());
+            bars.line_to(x, y);
+            if index == 0 {
+                envelope.move_to(x, y);
+            } else {
+                envelope.line_to(x, y);
+            }
+        }
+        let mut paint = vg::Paint::color(bar_color);
+        paint.set_line_width(BAR_WIDTH * dpi_scale);
+        canvas.stroke_path(&bars, &paint);
+        paint.set_line_width(dpi_scale);
+        canvas.stroke_path(&envelope, &paint);
+    }
+}
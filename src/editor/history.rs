@@ -0,0 +1,148 @@
+//! Undo/redo history for parameter edits made in the editor.
+
+use nih_plug::prelude::ParamPtr;
+use std::time::{Duration, Instant};
+
+/// Edits to the same parameter that follow each other within this window are merged into a single
+/// history entry, so a burst of scroll wheel steps or arrow key presses can be undone in one go.
+const COALESCE_WINDOW: Duration = Duration::from_millis(500);
+/// The maximum number of entries kept on the undo stack.
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Copy)]
+struct ParamChange {
+    ptr: ParamPtr,
+    before: f32,
+    after: f32,
+}
+
+#[derive(Debug)]
+struct HistoryEntry {
+    changes: Vec<ParamChange>,
+    last_edit: Instant,
+}
+
+/// Records every completed gesture, from the begin-set to the end-set event, as a single entry.
+/// Gestures that overlap, like the ones sent when loading a preset, end up in the same entry.
+#[derive(Debug, Default)]
+pub struct UndoHistory {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    /// Gestures that have been started but not yet finished.
+    open_gestures: Vec<ParamChange>,
+    /// Finished gestures that are waiting for the other overlapping gestures to finish.
+    pending_changes: Vec<ParamChange>,
+    /// Parameters that are currently being changed by an undo, a redo or the default preset. Their
+    /// gestures should not end up in the history.
+    replaying: Vec<ParamPtr>,
+}
+
+impl UndoHistory {
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn begin_gesture(&mut self, ptr: ParamPtr) {
+        if self.replaying.contains(&ptr) {
+            return;
+        }
+
+        // SAFETY: The editor only sends pointers to parameters that outlive it
+        let current = unsafe { ptr.unmodulated_normalized_value() };
+        self.open_gestures.retain(|change| change.ptr != ptr);
+        self.open_gestures.push(ParamChange {
+            ptr,
+            before: current,
+            after: current,
+        });
+    }
+
+    pub fn set_value(&mut self, ptr: ParamPtr, normalized: f32) {
+        if let Some(change) = self.open_gestures.iter_mut().find(|change| change.ptr == ptr) {
+            change.after = normalized;
+        }
+    }
+
+    pub fn end_gesture(&mut self, ptr: ParamPtr) {
+        if let Some(index) = self.replaying.iter().position(|replayed| *replayed == ptr) {
+            self.replaying.swap_remove(index);
+            return;
+        }
+
+        let Some(index) = self.open_gestures.iter().position(|change| change.ptr == ptr) else {
+            return;
+        };
+        let change = self.open_gestures.remove(index);
+        if change.before != change.after {
+            self.pending_changes.push(change);
+        }
+
+        if self.open_gestures.is_empty() && !self.pending_changes.is_empty() {
+            let changes = std::mem::take(&mut self.pending_changes);
+            self.push_entry(changes);
+        }
+    }
+
+    /// Keeps the next gesture for each of these parameters out of the history, e.g. for the
+    /// default preset that fresh instances load.
+    pub fn ignore_next_gestures(&mut self, ptrs: impl IntoIterator<Item = ParamPtr>) {
+        self.replaying.extend(ptrs);
+    }
+
+    /// Reverts the last entry. Returns the values that should be sent to the host.
+    pub fn undo(&mut self) -> Option<Vec<(ParamPtr, f32)>> {
+        let entry = self.undo_stack.pop()?;
+        let values: Vec<_> = entry
+            .changes
+            .iter()
+            .map(|change| (change.ptr, change.before))
+            .collect();
+        self.redo_stack.push(entry);
+        self.replaying.extend(values.iter().map(|(ptr, _)| *ptr));
+
+        Some(values)
+    }
+
+    /// Reapplies the last undone entry. Returns the values that should be sent to the host.
+    pub fn redo(&mut self) -> Option<Vec<(ParamPtr, f32)>> {
+        let entry = self.redo_stack.pop()?;
+        let values: Vec<_> = entry
+            .changes
+            .iter()
+            .map(|change| (change.ptr, change.after))
+            .collect();
+        self.undo_stack.push(entry);
+        self.replaying.extend(values.iter().map(|(ptr, _)| *ptr));
+
+        Some(values)
+    }
+
+    fn push_entry(&mut self, changes: Vec<ParamChange>) {
+        let now = Instant::now();
+        self.redo_stack.clear();
+
+        // Continuous edits of a single parameter, like scrolling over a knob, coalesce into the
+        // previous entry
+        if let ([change], Some(last)) = (changes.as_slice(), self.undo_stack.last_mut()) {
+            if let [last_change] = last.changes.as_mut_slice() {
+                if last_change.ptr == change.ptr && now - last.last_edit < COALESCE_WINDOW {
+                    last_change.after = change.after;
+                    last.last_edit = now;
+                    return;
+                }
+            }
+        }
+
+        self.undo_stack.push(HistoryEntry {
+            changes,
+            last_edit: now,
+        });
+        if self.undo_stack.len() > MAX_ENTRIES {
+            self.undo_stack.remove(0);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! The mouse and keyboard handling shared by the parameter knobs. This replaces vizia's `Knob` so
+//! every change, including wheel and arrow key steps, is wrapped in a host gesture.
+
+use nih_plug_vizia::vizia::prelude::*;
+
+/// How far the value moves per pixel of vertical drag.
+const DRAG_SCALAR: f32 = 0.0042;
+/// How far the value moves per wheel notch or arrow key press.
+const STEP_SCALAR: f32 = 0.01;
+/// Holding Shift scales all of the above by this much for fine adjustments.
+const FINE_SCALAR: f32 = 0.1;
+
+/// Emitted by [`KnobInput`] and handled by the knob widget that owns the parameter.
+#[derive(Debug)]
+pub enum KnobEvent {
+    BeginSetParam,
+    SetParam(f32),
+    EndSetParam,
+    /// Double clicking the knob opens a text box for typing in a value.
+    BeginTextEntry,
+    TextEntry(String),
+    CancelTextEntry,
+}
+
+/// Drag vertically to change the value, hold Shift for fine control. Ctrl/Cmd-click or Alt-click
+/// resets the value to its default, double clicking starts text entry, and the mouse wheel and the
+/// arrow keys move the value in small steps.
+pub struct KnobInput<L> {
+    lens: L,
+    default_normal: f32,
+
+    is_dragging: bool,
+    prev_drag_y: f32,
+    /// The value while dragging. Tracked here instead of read back from the parameter so small
+    /// fine-adjust steps don't get lost to rounding in the host.
+    continuous_normal: f32,
+}
+
+impl<L: Lens<Target = f32>> KnobInput<L> {
+    pub fn new<F, V: View>(
+        cx: &mut Context,
+        default_normal: f32,
+        lens: L,
+        content: F,
+    ) -> Handle<'_, Self>
+    where
+        F: 'static + Fn(&mut Context, L) -> Handle<V>,
+    {
+        Self {
+            lens,
+            default_normal,
+
+            is_dragging: false,
+            prev_drag_y: 0.0,
+            continuous_normal: lens.get(cx),
+        }
+        .build(cx, move |cx| {
+            ZStack::new(cx, move |cx| {
+                (content)(cx, lens).width(Percentage(100.0)).height(Percentage(100.0));
+            });
+        })
+        .navigable(true)
+    }
+
+    /// Moves the value by `delta` in a single gesture, for the wheel and the arrow keys.
+    fn step(&mut self, cx: &mut EventContext, delta: f32) {
+        let scalar = if cx.modifiers().contains(Modifiers::SHIFT) { FINE_SCALAR } else { 1.0 };
+        let new_normal = (self.lens.get(cx) + delta * scalar).clamp(0.0, 1.0);
+
+        cx.emit(KnobEvent::BeginSetParam);
+        cx.emit(KnobEvent::SetParam(new_normal));
+        cx.emit(KnobEvent::EndSetParam);
+    }
+}
+
+impl<L: Lens<Target = f32>> View for KnobInput<L> {
+    fn element(&self) -> Option<&'static str> {
+        Some("knob")
+    }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|window_event, meta| match window_event {
+            WindowEvent::MouseDown(MouseButton::Left) => {
+                if cx.is_disabled() {
+                    return;
+                }
+
+                cx.focus_with_visibility(false);
+                if cx.modifiers().intersects(Modifiers::CTRL | Modifiers::LOGO | Modifiers::ALT) {
+                    cx.emit(KnobEvent::BeginSetParam);
+                    cx.emit(KnobEvent::SetParam(self.default_normal));
+                    cx.emit(KnobEvent::EndSetParam);
+                } else {
+                    self.is_dragging = true;
+                    self.prev_drag_y = cx.mouse().left.pos_down.1;
+                    self.continuous_normal = self.lens.get(cx);
+
+                    cx.capture();
+                    cx.emit(KnobEvent::BeginSetParam);
+                }
+                meta.consume();
+            }
+
+            WindowEvent::MouseUp(MouseButton::Left) => {
+                if self.is_dragging {
+                    self.is_dragging = false;
+
+                    cx.release();
+                    cx.emit(KnobEvent::EndSetParam);
+                    meta.consume();
+                }
+            }
+
+            WindowEvent::MouseMove(_, y) => {
+                if self.is_dragging {
+                    let mut delta_normal = (*y - self.prev_drag_y) * DRAG_SCALAR;
+                    self.prev_drag_y = *y;
+                    if cx.modifiers().contains(Modifiers::SHIFT) {
+                        delta_normal *= FINE_SCALAR;
+                    }
+
+                    self.continuous_normal =
+                        (self.continuous_normal - delta_normal).clamp(0.0, 1.0);
+                    cx.emit(KnobEvent::SetParam(self.continuous_normal));
+                }
+            }
+
+            WindowEvent::MouseDoubleClick(MouseButton::Left) => {
+                if cx.is_disabled() {
+                    return;
+                }
+
+                // The second click doesn't send a mouse down, so there's no drag to end here
+                cx.emit(KnobEvent::BeginTextEntry);
+                meta.consume();
+            }
+
+            WindowEvent::MouseScroll(_, y) => {
+                if *y != 0.0 && !self.is_dragging && !cx.is_disabled() {
+                    self.step(cx, y.signum() * STEP_SCALAR);
+                    meta.consume();
+                }
+            }
+
+            WindowEvent::KeyDown(Code::ArrowUp | Code::ArrowRight, _) => {
+                if !cx.is_disabled() {
+                    self.step(cx, STEP_SCALAR);
+                    meta.consume();
+                }
+            }
+
+            WindowEvent::KeyDown(Code::ArrowDown | Code::ArrowLeft, _) => {
+                if !cx.is_disabled() {
+                    self.step(cx, -STEP_SCALAR);
+                    meta.consume();
+                }
+            }
+
+            _ => {}
+        });
+    }
+}
+
+/// Builds the text box that replaces a knob's value label while a value is being typed in. Enter
+/// applies the value, while Escape or clicking somewhere else cancels.
+pub fn build_text_entry<L>(cx: &mut Context, display_value: L) -> Handle<'_, Textbox<L>>
+where
+    L: Lens<Target = String>,
+{
+    Textbox::new(cx, display_value)
+        .class("knob-value-entry")
+        .on_submit(|cx, string, is_enter| {
+            if is_enter {
+                cx.emit(KnobEvent::TextEntry(string));
+            } else {
+                cx.emit(KnobEvent::CancelTextEntry);
+            }
+        })
+        .on_cancel(|cx| cx.emit(KnobEvent::CancelTextEntry))
+        .on_build(|cx| {
+            cx.emit(TextEvent::StartEdit);
+            cx.emit(TextEvent::SelectAll);
+        })
+}
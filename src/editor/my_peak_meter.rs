@@ -1,151 +1,325 @@
-//! A super simple peak meter widget.
+//! A peak and RMS meter widget with a clip indicator.
 
 use nih_plug::prelude::util;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 use nih_plug_vizia::vizia::prelude::*;
 use nih_plug_vizia::vizia::vg;
 
+use crate::meters::ChannelLevels;
+
 /// The thickness of a tick inside of the peak meter's bar.
 const TICK_WIDTH: f32 = 1.0;
 /// The gap between individual ticks.
 const TICK_GAP: f32 = 1.0;
+/// The part of the bar between the RMS level and the peak level is drawn at this opacity.
+const PEAK_OPACITY: f32 = 0.45;
+
+/// The decibel value corresponding to the start of the bar, unless the `--meter-min` property
+/// says otherwise.
+const DEFAULT_MIN_TICK: f32 = -90.0;
+/// The decibel value corresponding to the end of the bar, unless the `--meter-max` property says
+/// otherwise.
+const DEFAULT_MAX_TICK: f32 = 20.0;
+/// The ticks that will be shown next to the peak meter's bar, unless the `--meter-ticks` property
+/// says otherwise. The first value is shown as -infinity, and at the last position we'll draw the
+/// `dBFS` string.
+const DEFAULT_TEXT_TICKS: [f32; 6] = [-80.0, -60.0, -40.0, -20.0, 0.0, 12.0];
+
+/// A peak meter for one channel. The bar shows the RMS level at full strength with the peak level
+/// beyond it, and the clip light at the end of the bar stays lit until it's clicked.
+///
+/// The scale can be changed from the stylesheet with the `--meter-min` and `--meter-max`
+/// properties for the bar's range in decibels, and `--meter-ticks` for the labeled ticks.
+pub struct MyPeakMeter<L>
+where
+    L: Lens<Target = Arc<ChannelLevels>>,
+{
+    levels: L,
+}
 
-/// The decibel value corresponding to the very left of the bar.
-const MIN_TICK: f32 = -90.0;
-/// The decibel value corresponding to the very right of the bar.
-const MAX_TICK: f32 = 20.0;
-/// The ticks that will be shown beneath the peak meter's bar. The first value is shown as
-/// -infinity, and at the last position we'll draw the `dBFS` string.
-const TEXT_TICKS: [i32; 6] = [-80, -60, -40, -20, 0, 12];
+enum MyPeakMeterEvent {
+    ResetClip,
+}
 
-/// A simple horizontal peak meter.
-///
-/// TODO: There are currently no styling options at all
-/// TODO: Vertical peak meter, this is just a proof of concept to fit the gain GUI example.
-pub struct MyPeakMeter;
+/// The range and the labeled ticks of a meter's scale, in decibels.
+#[derive(Debug, Clone, PartialEq)]
+struct MeterScale {
+    min_db: f32,
+    max_db: f32,
+    ticks: Vec<f32>,
+}
+
+impl Default for MeterScale {
+    fn default() -> Self {
+        Self {
+            min_db: DEFAULT_MIN_TICK,
+            max_db: DEFAULT_MAX_TICK,
+            ticks: DEFAULT_TEXT_TICKS.to_vec(),
+        }
+    }
+}
+
+impl Data for MeterScale {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl MeterScale {
+    /// Reads the scale from the stylesheet, falling back to the defaults for anything that isn't
+    /// set or doesn't make sense.
+    fn from_style(cx: &DrawContext) -> Self {
+        let default = Self::default();
+        let single_value = |name: &str, default: f32| {
+            cx.custom_property(name)
+                .and_then(|values| values.first().copied())
+                .unwrap_or(default)
+        };
+
+        let min_db = single_value("--meter-min", default.min_db);
+        let max_db = single_value("--meter-max", default.max_db);
+        if min_db >= max_db {
+            return default;
+        }
 
-/// The bar bit for the peak meter, manually drawn using vertical lines.
-struct MyPeakMeterBar<L, P>
+        let ticks = cx
+            .custom_property("--meter-ticks")
+            .filter(|ticks| !ticks.is_empty())
+            .map_or(default.ticks, |ticks| ticks.to_vec());
+
+        Self {
+            min_db,
+            max_db,
+            ticks,
+        }
+    }
+
+    /// Where `db` falls on the scale, from 0 at the minimum to 1 at the maximum.
+    fn fraction(&self, db: f32) -> f32 {
+        (db - self.min_db) / (self.max_db - self.min_db)
+    }
+}
+
+/// The bar bit for the peak meter, manually drawn using lines across the bar.
+struct MyPeakMeterBar<L, R, P>
 where
     L: Lens<Target = f32>,
+    R: Lens<Target = f32>,
     P: Lens<Target = f32>,
 {
     level_dbfs: L,
+    rms_dbfs: R,
     peak_dbfs: P,
+    orientation: Orientation,
+    /// The scale the bar was last drawn with, so the tick labels can follow the stylesheet.
+    scale: Rc<RefCell<MeterScale>>,
 }
 
-impl MyPeakMeter {
-    /// Creates a new [`MyPeakMeter`] for the given value in decibel, optionally holding the peak
-    /// value for a certain amount of time.
-    pub fn new<L>(cx: &mut Context, level_dbfs: L, hold_time: Option<Duration>) -> Handle<'_, Self>
-    where
-        L: Lens<Target = f32>,
-    {
-        Self.build(cx, |cx| {
-            // Now for something that may be illegal under some jurisdictions. If a hold time is
-            // given, then we'll build a new lens that always gives the held peak level for the
-            // current moment in time by mutating some values captured into the mapping closure.
-            let held_peak_value_db = Cell::new(f32::MIN);
-            let last_held_peak_value: Cell<Option<Instant>> = Cell::new(None);
-            let peak_dbfs = level_dbfs.map(move |level| -> f32 {
-                match hold_time {
-                    Some(hold_time) => {
-                        let mut peak_level = held_peak_value_db.get();
-                        let peak_time = last_held_peak_value.get();
-
-                        let now = Instant::now();
-                        if *level >= peak_level
-                            || peak_time.is_none()
-                            || now > peak_time.unwrap() + hold_time
-                        {
-                            peak_level = *level;
-                            held_peak_value_db.set(peak_level);
-                            last_held_peak_value.set(Some(now));
+impl<L> MyPeakMeter<L>
+where
+    L: Lens<Target = Arc<ChannelLevels>>,
+{
+    /// Creates a new [`MyPeakMeter`] for a channel's levels, optionally holding the peak value for
+    /// a certain amount of time.
+    pub fn new(
+        cx: &mut Context,
+        levels: L,
+        hold_time: Option<Duration>,
+        orientation: Orientation,
+    ) -> Handle<'_, Self> {
+        Self { levels }
+            .build(cx, |cx| {
+                let level_dbfs = levels.map(|levels| util::gain_to_db(levels.peak()));
+                let rms_dbfs = levels.map(|levels| util::gain_to_db(levels.rms()));
+
+                // Now for something that may be illegal under some jurisdictions. If a hold time is
+                // given, then we'll build a new lens that always gives the held peak level for the
+                // current moment in time by mutating some values captured into the mapping closure.
+                let held_peak_value_db = Cell::new(f32::MIN);
+                let last_held_peak_value: Cell<Option<Instant>> = Cell::new(None);
+                let peak_dbfs = level_dbfs.map(move |level| -> f32 {
+                    match hold_time {
+                        Some(hold_time) => {
+                            let mut peak_level = held_peak_value_db.get();
+                            let peak_time = last_held_peak_value.get();
+
+                            let now = Instant::now();
+                            if *level >= peak_level
+                                || peak_time.is_none()
+                                || now > peak_time.unwrap() + hold_time
+                            {
+                                peak_level = *level;
+                                held_peak_value_db.set(peak_level);
+                                last_held_peak_value.set(Some(now));
+                            }
+
+                            peak_level
                         }
-
-                        peak_level
+                        None => util::MINUS_INFINITY_DB,
                     }
-                    None => util::MINUS_INFINITY_DB,
-                }
-            });
+                });
+
+                // The same trick again: the bar reads the scale from the stylesheet while drawing,
+                // and the ticks are rebuilt whenever that scale changes
+                let scale = Rc::new(RefCell::new(MeterScale::default()));
+                let bar_scale = scale.clone();
+                HStack::new(cx, move |cx| {
+                    // The clip light goes at the end of the bar, so on top for vertical meters
+                    if orientation == Orientation::Vertical {
+                        build_clip_light(cx, levels, orientation);
+                    }
+                    MyPeakMeterBar {
+                        level_dbfs,
+                        rms_dbfs,
+                        peak_dbfs,
+                        orientation,
+                        scale: bar_scale,
+                    }
+                        .build(cx, |_| {})
+                        .class("bar");
+                    if orientation == Orientation::Horizontal {
+                        build_clip_light(cx, levels, orientation);
+                    }
+                })
+                    .layout_type(match orientation {
+                        Orientation::Horizontal => LayoutType::Row,
+                        Orientation::Vertical => LayoutType::Column,
+                    })
+                    .col_between(Pixels(2.0))
+                    .row_between(Pixels(2.0))
+                    .class("meter-bar-row");
+
+                Binding::new(
+                    cx,
+                    levels.map(move |_| scale.borrow().clone()),
+                    move |cx, scale| {
+                        let scale = scale.get(cx);
+                        build_ticks(cx, &scale, orientation);
+                    },
+                );
+            })
+            .layout_type(match orientation {
+                Orientation::Horizontal => LayoutType::Column,
+                Orientation::Vertical => LayoutType::Row,
+            })
+            .toggle_class("vertical", orientation == Orientation::Vertical)
+            .overflow(Overflow::Visible)
+    }
+}
 
-            MyPeakMeterBar {
-                level_dbfs,
-                peak_dbfs,
-            }
-                .build(cx, |_| {})
-                .class("bar");
-
-            ZStack::new(cx, |cx| {
-                const WIDTH_PCT: f32 = 50.0;
-                for tick_db in TEXT_TICKS {
-                    let tick_fraction = (tick_db as f32 - MIN_TICK) / (MAX_TICK - MIN_TICK);
-                    let tick_pct = tick_fraction * 100.0;
-                    // We'll shift negative numbers slightly to the left so they look more centered
-                    let needs_minus_offset = tick_db < 0;
-
-                    ZStack::new(cx, |cx| {
-                        let first_tick = tick_db == TEXT_TICKS[0];
-                        let last_tick = tick_db == TEXT_TICKS[TEXT_TICKS.len() - 1];
-
-                        if !last_tick {
-                            // FIXME: This is not aligned to the pixel grid and some ticks will look
-                            //        blurry, is there a way to fix this?
-                            Element::new(cx).class("ticks__tick");
-                        }
+/// Builds the clip light, which resets the clip indicator when clicked.
+fn build_clip_light<L>(cx: &mut Context, levels: L, orientation: Orientation)
+where
+    L: Lens<Target = Arc<ChannelLevels>>,
+{
+    let clip_light = Element::new(cx)
+        .class("clip-light")
+        .toggle_class("clipped", levels.map(|levels| levels.is_clipped()))
+        .on_press(|cx| cx.emit(MyPeakMeterEvent::ResetClip));
+    match orientation {
+        Orientation::Horizontal => clip_light.width(Pixels(8.0)).height(Stretch(1.0)),
+        Orientation::Vertical => clip_light.width(Stretch(1.0)).height(Pixels(8.0)),
+    };
+}
 
-                        let font_size = {
-                            let event_cx = EventContext::new(cx);
-                            event_cx.font_size() * event_cx.scale_factor()
-                        };
-                        let label = if first_tick {
-                            Label::new(cx, "-inf")
-                                .class("ticks__label")
-                                .class("ticks__label--inf")
-                        } else if last_tick {
-                            // This is only inclued in the array to make positioning this easier
-                            Label::new(cx, "dBFS")
-                                .class("ticks__label")
-                                .class("ticks__label--dbfs")
-                        } else {
-                            Label::new(cx, &tick_db.to_string()).class("ticks__label")
-                        }
-                            .overflow(Overflow::Visible);
+/// Builds the tick marks and their labels for `scale`.
+fn build_ticks(cx: &mut Context, scale: &MeterScale, orientation: Orientation) {
+    ZStack::new(cx, |cx| {
+        const SIZE_PCT: f32 = 50.0;
+        let first_tick = scale.ticks.first().copied();
+        let last_tick = scale.ticks.last().copied();
+        for &tick_db in &scale.ticks {
+            let tick_pct = scale.fraction(tick_db) * 100.0;
+            // We'll shift negative numbers slightly to the left so they look more centered
+            let needs_minus_offset = tick_db < 0.0 && orientation == Orientation::Horizontal;
+
+            let tick = ZStack::new(cx, |cx| {
+                let is_first_tick = Some(tick_db) == first_tick;
+                let is_last_tick = Some(tick_db) == last_tick;
+
+                if !is_last_tick {
+                    // FIXME: This is not aligned to the pixel grid and some ticks will look
+                    //        blurry, is there a way to fix this?
+                    Element::new(cx).class("ticks__tick");
+                }
 
-                        if needs_minus_offset {
-                            label.child_right(Pixels(font_size * 0.15));
-                        }
-                    })
-                        .height(Stretch(1.0))
-                        .left(Percentage(tick_pct - (WIDTH_PCT / 2.0)))
-                        .width(Percentage(WIDTH_PCT))
-                        .child_left(Stretch(1.0))
-                        .child_right(Stretch(1.0))
-                        .overflow(Overflow::Visible);
+                let font_size = {
+                    let event_cx = EventContext::new(cx);
+                    event_cx.font_size() * event_cx.scale_factor()
+                };
+                let label = if is_first_tick {
+                    Label::new(cx, "-inf")
+                        .class("ticks__label")
+                        .class("ticks__label--inf")
+                } else if is_last_tick {
+                    // This is only inclued in the array to make positioning this easier
+                    Label::new(cx, "dBFS")
+                        .class("ticks__label")
+                        .class("ticks__label--dbfs")
+                } else {
+                    let text = (tick_db.round() as i32).to_string();
+                    Label::new(cx, &text).class("ticks__label")
+                }
+                    .overflow(Overflow::Visible);
+
+                if needs_minus_offset {
+                    label.child_right(Pixels(font_size * 0.15));
                 }
             })
-                .class("ticks")
                 .overflow(Overflow::Visible);
-        })
-            .overflow(Overflow::Visible)
-    }
+
+            match orientation {
+                Orientation::Horizontal => tick
+                    .height(Stretch(1.0))
+                    .left(Percentage(tick_pct - (SIZE_PCT / 2.0)))
+                    .width(Percentage(SIZE_PCT))
+                    .child_left(Stretch(1.0))
+                    .child_right(Stretch(1.0)),
+                // Higher levels go further up
+                Orientation::Vertical => tick
+                    .width(Stretch(1.0))
+                    .top(Percentage(100.0 - tick_pct - (SIZE_PCT / 2.0)))
+                    .height(Percentage(SIZE_PCT))
+                    .child_top(Stretch(1.0))
+                    .child_bottom(Stretch(1.0)),
+            };
+        }
+    })
+        .class("ticks")
+        .overflow(Overflow::Visible);
 }
 
-impl View for MyPeakMeter {
+impl<L> View for MyPeakMeter<L>
+where
+    L: Lens<Target = Arc<ChannelLevels>>,
+{
     fn element(&self) -> Option<&'static str> {
         Some("my-peak-meter")
     }
+
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|meter_event, meta| match meter_event {
+            MyPeakMeterEvent::ResetClip => {
+                self.levels.get(cx).reset_clip();
+                meta.consume();
+            }
+        });
+    }
 }
 
-impl<L, P> View for MyPeakMeterBar<L, P>
+impl<L, R, P> View for MyPeakMeterBar<L, R, P>
 where
     L: Lens<Target = f32>,
+    R: Lens<Target = f32>,
     P: Lens<Target = f32>,
 {
     fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
         let level_dbfs = self.level_dbfs.get(cx);
+        let rms_dbfs = self.rms_dbfs.get(cx);
         let peak_dbfs = self.peak_dbfs.get(cx);
 
         // These basics are taken directly from the default implementation of this function
@@ -154,6 +328,11 @@ where
             return;
         }
 
+        let scale = MeterScale::from_style(cx);
+        if *self.scale.borrow() != scale {
+            *self.scale.borrow_mut() = scale.clone();
+        }
+
         // TODO: It would be cool to allow the text color property to control the gradient here. For
         //       now we'll only support basic background colors and borders.
         let background_color = cx.background_color();
@@ -184,30 +363,51 @@ where
         canvas.fill_path(&path, &paint);
 
         // And now for the fun stuff. We'll try to not overlap the border, but we'll draw that last
-        // just in case.
+        // just in case. Positions along the bar are measured from its start, which is the left
+        // edge for horizontal meters and the bottom edge for vertical ones.
         let bar_bounds = bounds.shrink(border_width / 2.0);
-        let bar_ticks_start_x = bar_bounds.left().floor() as i32;
-        let bar_ticks_end_x = bar_bounds.right().ceil() as i32;
+        let (bar_ticks_start, bar_ticks_end) = match self.orientation {
+            Orientation::Horizontal => {
+                (bar_bounds.left().floor() as i32, bar_bounds.right().ceil() as i32)
+            }
+            Orientation::Vertical => {
+                (bar_bounds.top().floor() as i32, bar_bounds.bottom().ceil() as i32)
+            }
+        };
+        let bar_length = (bar_ticks_end - bar_ticks_start) as f32;
+        // Draws a line across the bar at `offset` from the bar's start
+        let line_across = |offset: f32| {
+            let mut path = vg::Path::new();
+            match self.orientation {
+                Orientation::Horizontal => {
+                    let x = bar_ticks_start as f32 + offset;
+                    path.move_to(x, bar_bounds.top());
+                    path.line_to(x, bar_bounds.bottom());
+                }
+                Orientation::Vertical => {
+                    let y = bar_ticks_end as f32 - offset;
+                    path.move_to(bar_bounds.left(), y);
+                    path.line_to(bar_bounds.right(), y);
+                }
+            }
+            path
+        };
 
         // NOTE: We'll scale this with the nearest integer DPI ratio. That way it will still look
         //       good at 2x scaling, and it won't look blurry at 1.x times scaling.
         let dpi_scale = cx.logical_to_physical(1.0).floor().max(1.0);
-        let bar_tick_coordinates = (bar_ticks_start_x..bar_ticks_end_x)
-            .step_by(((TICK_WIDTH + TICK_GAP) * dpi_scale).round() as usize);
-        for tick_x in bar_tick_coordinates {
-            let tick_fraction =
-                (tick_x - bar_ticks_start_x) as f32 / (bar_ticks_end_x - bar_ticks_start_x) as f32;
-            let tick_db = (tick_fraction * (MAX_TICK - MIN_TICK)) + MIN_TICK;
+        let bar_tick_offsets =
+            (0..bar_length as i32).step_by(((TICK_WIDTH + TICK_GAP) * dpi_scale).round() as usize);
+        for tick_offset in bar_tick_offsets {
+            let tick_fraction = tick_offset as f32 / bar_length;
+            let tick_db = (tick_fraction * (scale.max_db - scale.min_db)) + scale.min_db;
             if tick_db > level_dbfs {
                 break;
             }
 
             // femtovg draws paths centered on these coordinates, so in order to be pixel perfect we
             // need to account for that. Otherwise the ticks will be 2px wide instead of 1px.
-            let mut path = vg::Path::new();
-            path.move_to(tick_x as f32 + (dpi_scale / 2.0), bar_bounds.top());
-            path.line_to(tick_x as f32 + (dpi_scale / 2.0), bar_bounds.bottom());
-
+            let path = line_across(tick_offset as f32 + (dpi_scale / 2.0));
 
             let start = Color::rgba(25,25,38,100);
             let end = Color::rgba(255,178,0,100);
@@ -226,26 +426,21 @@ where
             let g = start_g + (end_g - start_g) * tick_fraction;
             let b = start_b + (end_b - start_b) * tick_fraction;
 
-            // 3. Create the paint (rgbaf expects 0.0 to 1.0)
-            let mut paint = vg::Paint::color(vg::Color::rgbaf(r, g, b, opacity));
+            // 3. Create the paint (rgbaf expects 0.0 to 1.0). Everything above the RMS level is
+            //    only the peak, which is drawn fainter.
+            let alpha = if tick_db > rms_dbfs { opacity * PEAK_OPACITY } else { opacity };
+            let mut paint = vg::Paint::color(vg::Color::rgbaf(r, g, b, alpha));
 
             paint.set_line_width(TICK_WIDTH * dpi_scale);
             canvas.stroke_path(&path, &paint);
         }
 
         // Draw the hold peak value if the hold time option has been set
-        let db_to_x_coord = |db: f32| {
-            let tick_fraction = (db - MIN_TICK) / (MAX_TICK - MIN_TICK);
-            bar_ticks_start_x as f32
-                + ((bar_ticks_end_x - bar_ticks_start_x) as f32 * tick_fraction).round()
-        };
-        if (MIN_TICK..MAX_TICK).contains(&peak_dbfs) {
+        if (scale.min_db..scale.max_db).contains(&peak_dbfs) {
             // femtovg draws paths centered on these coordinates, so in order to be pixel perfect we
             // need to account for that. Otherwise the ticks will be 2px wide instead of 1px.
-            let peak_x = db_to_x_coord(peak_dbfs);
-            let mut path = vg::Path::new();
-            path.move_to(peak_x + (dpi_scale / 2.0), bar_bounds.top());
-            path.line_to(peak_x + (dpi_scale / 2.0), bar_bounds.bottom());
+            let peak_offset = (bar_length * scale.fraction(peak_dbfs)).round();
+            let path = line_across(peak_offset + (dpi_scale / 2.0));
 
             let mut paint = vg::Paint::color(vg::Color::rgbaf(0.3, 0.3, 0.3, opacity));
             paint.set_line_width(TICK_WIDTH * dpi_scale);
@@ -1,17 +1,18 @@
-use nih_plug::prelude::Param;
+use nih_plug::prelude::{Param, ParamPtr};
 use nih_plug_vizia::vizia::prelude::*;
 use nih_plug_vizia::widgets::param_base::ParamWidgetBase;
 
-#[derive(Debug)]
-pub enum ParamEvent {
-    BeginSetParam,
-    SetParam(f32),
-    EndSetParam,
-}
+use super::knob_input::{self, KnobEvent, KnobInput};
+use super::{Data, EditorEvent};
+use crate::lfo::{self, LfoTarget};
 
 #[derive(Lens)]
 pub struct ParamKnob {
     param_base: ParamWidgetBase,
+    /// Right-clicking the knob opens the MIDI learn menu for this parameter.
+    param_ptr: ParamPtr,
+    /// Whether the value label is replaced by a text box after a double click.
+    text_input_active: bool,
 }
 
 impl ParamKnob {
@@ -27,30 +28,42 @@ impl ParamKnob {
         P: Param + 'static,
         FMap: Fn(&Params) -> &P + Copy + 'static,
     {
+        let param_ptr = params.map(move |params| params_to_param(params).as_ptr()).get(cx);
+        // Knobs for parameters the LFOs can modulate show where the LFOs currently put them
+        let lfo_target = Data::tape_data
+            .map(move |tape_data| LfoTarget::of_param(tape_data, param_ptr))
+            .get(cx);
+
         Self {
             param_base: ParamWidgetBase::new(cx, params.clone(), params_to_param),
+            param_ptr,
+            text_input_active: false,
         }
         .build(
             cx,
             ParamWidgetBase::build_view(params, params_to_param, move |cx, param_data| {
                 VStack::new(cx, |cx| {
-                    Label::new(
-                        cx,
-                        params.map(move |params| {
-                            params_to_param(params)
-                                .normalized_value_to_string(
-                                    params_to_param(params)
-                                        .modulated_normalized_value()
-                                        .to_owned(),
-                                    true,
-                                )
-                                .to_owned()
-                        }),
-                    )
-                    .class("param-label")
-                    .space(Stretch(0.8));
+                    let display_value = params.map(move |params| {
+                        params_to_param(params)
+                            .normalized_value_to_string(
+                                params_to_param(params).modulated_normalized_value().to_owned(),
+                                true,
+                            )
+                            .to_owned()
+                    });
+                    Binding::new(cx, ParamKnob::text_input_active, move |cx, text_input_active| {
+                        if text_input_active.get(cx) {
+                            knob_input::build_text_entry(cx, display_value)
+                                .class("param-label")
+                                .space(Stretch(0.8));
+                        } else {
+                            Label::new(cx, display_value)
+                                .class("param-label")
+                                .space(Stretch(0.8));
+                        }
+                    });
 
-                    Knob::custom(
+                    KnobInput::new(
                         cx,
                         param_data.param().default_normalized_value(),
                         params.map(move |params| {
@@ -77,22 +90,30 @@ impl ParamKnob {
                                 )
                                 .value(lens)
                                 .class("knob-arc");
+
+                                if let Some(target) = lfo_target {
+                                    let modulated = Data::tape_data
+                                        .map(move |tape_data| lfo::modulated_normalized(tape_data, target));
+                                    ArcTrack::new(
+                                        cx,
+                                        centered,
+                                        Percentage(500.0),
+                                        Percentage(6.),
+                                        -150.,
+                                        150.,
+                                        KnobMode::Continuous,
+                                    )
+                                    .value(modulated.map(|value| value.unwrap_or_default()))
+                                    .display(modulated.map(Option::is_some))
+                                    .class("knob-mod-arc");
+                                }
                             })
                             .child_space(Stretch(1.0))
                             .width(Pixels(160.0))
                             .height(Pixels(160.0))
                         },
                     )
-                    .space(Stretch(0.8))
-                    .on_mouse_down(move |cx, _button| {
-                        cx.emit(ParamEvent::BeginSetParam);
-                    })
-                    .on_changing(move |cx, val| {
-                        cx.emit(ParamEvent::SetParam(val));
-                    })
-                    .on_mouse_up(move |cx, _button| {
-                        cx.emit(ParamEvent::EndSetParam);
-                    });
+                    .space(Stretch(0.8));
 
                     Label::new(
                         cx,
@@ -111,15 +132,45 @@ impl ParamKnob {
 impl View for ParamKnob {
     fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
         event.map(|param_change_event, _| match param_change_event {
-            ParamEvent::BeginSetParam => {
+            KnobEvent::BeginSetParam => {
                 self.param_base.begin_set_parameter(cx);
             }
-            ParamEvent::SetParam(val) => {
+            KnobEvent::SetParam(val) => {
                 self.param_base.set_normalized_value(cx, *val);
             }
-            ParamEvent::EndSetParam => {
+            KnobEvent::EndSetParam => {
                 self.param_base.end_set_parameter(cx);
             }
+            KnobEvent::BeginTextEntry => {
+                // Disabled knobs, like the ghost knobs, can't be typed into
+                if !cx.is_disabled() {
+                    self.text_input_active = true;
+                }
+            }
+            KnobEvent::TextEntry(string) => {
+                if let Some(normalized) = self.param_base.string_to_normalized_value(string) {
+                    self.param_base.begin_set_parameter(cx);
+                    self.param_base.set_normalized_value(cx, normalized);
+                    self.param_base.end_set_parameter(cx);
+                }
+                self.text_input_active = false;
+            }
+            KnobEvent::CancelTextEntry => {
+                self.text_input_active = false;
+            }
+        });
+
+        event.map(|window_event, meta| {
+            // Disabled knobs, like the ghost knobs, stand in for hidden parameters that can't be
+            // mapped
+            if let WindowEvent::MouseDown(MouseButton::Right) = window_event {
+                if cx.is_disabled() {
+                    return;
+                }
+
+                cx.emit(EditorEvent::OpenMidiMenu(self.param_ptr));
+                meta.consume();
+            }
         });
     }
 }
@@ -0,0 +1,132 @@
+//! The tape saturation's transfer curve at the current Gain setting, with a dot at the level that's
+//! currently going into the saturation.
+
+use nih_plug::prelude::Param;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::{drive_tape_classic, makeup_gain, AtomicF32, TapeParams};
+
+/// The input levels the curve covers, from minus this to plus this.
+const INPUT_RANGE: f32 = 1.5;
+/// The output levels the curve covers, from minus this to plus this.
+const OUTPUT_RANGE: f32 = 1.0;
+const NUM_POINTS: usize = 128;
+/// The radius of the operating point's dot.
+const DOT_RADIUS: f32 = 3.5;
+
+/// The curve uses the font color, the axes and the unity line use the border color and the
+/// operating point uses the outline color. The curve includes the makeup gain, so it shows the
+/// level that actually ends up on the output.
+pub struct SaturationView<P, D>
+where
+    P: Lens<Target = Arc<TapeParams>>,
+    D: Lens<Target = Arc<AtomicF32>>,
+{
+    params: P,
+    drive_level: D,
+}
+
+impl<P, D> SaturationView<P, D>
+where
+    P: Lens<Target = Arc<TapeParams>>,
+    D: Lens<Target = Arc<AtomicF32>>,
+{
+    pub fn new(cx: &mut Context, params: P, drive_level: D) -> Handle<Self> {
+        Self {
+            params,
+            drive_level,
+        }
+        .build(cx, |_| {})
+        .bind(params.map(|params| params.gain.value()), |mut handle, _| {
+            handle.needs_redraw()
+        })
+        .bind(
+            drive_level.map(|level| level.load(Ordering::Relaxed)),
+            |mut handle, _| handle.needs_redraw(),
+        )
+    }
+}
+
+impl<P, D> View for SaturationView<P, D>
+where
+    P: Lens<Target = Arc<TapeParams>>,
+    D: Lens<Target = Arc<AtomicF32>>,
+{
+    fn element(&self) -> Option<&'static str> {
+        Some("saturation-view")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let opacity = cx.opacity();
+        let color = |color: Color| {
+            let mut color: vg::Color = color.into();
+            color.set_alphaf(color.a * opacity);
+            color
+        };
+        let background_color = color(cx.background_color());
+        let curve_color = color(cx.font_color());
+        let grid_color = color(cx.border_color());
+        let dot_color = color(cx.outline_color());
+        let dpi_scale = cx.logical_to_physical(1.0).max(1.0);
+
+        let mut path = vg::Path::new();
+        path.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&path, &vg::Paint::color(background_color));
+
+        let input_to_x = |input: f32| bounds.center().0 + (input / INPUT_RANGE) * bounds.w * 0.5;
+        let output_to_y = |output: f32| {
+            let fraction = output.clamp(-OUTPUT_RANGE, OUTPUT_RANGE) / OUTPUT_RANGE;
+            bounds.center().1 - fraction * bounds.h * 0.5
+        };
+
+        // The axes, and the line a signal would follow without any saturation
+        let mut grid = vg::Path::new();
+        grid.move_to(bounds.left(), bounds.center().1);
+        grid.line_to(bounds.right(), bounds.center().1);
+        grid.move_to(bounds.center().0, bounds.top());
+        grid.line_to(bounds.center().0, bounds.bottom());
+        grid.move_to(input_to_x(-OUTPUT_RANGE), output_to_y(-OUTPUT_RANGE));
+        grid.line_to(input_to_x(OUTPUT_RANGE), output_to_y(OUTPUT_RANGE));
+        let mut paint = vg::Paint::color(grid_color);
+        paint.set_line_width(0.5 * dpi_scale);
+        canvas.stroke_path(&grid, &paint);
+
+        let params = self.params.get(cx);
+        let gain = params.gain.value();
+        let makeup_gain = makeup_gain(gain);
+        let transfer = |input: f32| drive_tape_classic(gain, input) * makeup_gain;
+
+        let mut curve = vg::Path::new();
+        for point in 0..=NUM_POINTS {
+            let input = -INPUT_RANGE + 2.0 * INPUT_RANGE * (point as f32 / NUM_POINTS as f32);
+            let (x, y) = (input_to_x(input), output_to_y(transfer(input)));
+            if point == 0 {
+                curve.move_to(x, y);
+            } else {
+                curve.line_to(x, y);
+            }
+        }
+        let mut paint = vg::Paint::color(curve_color);
+        paint.set_line_width(1.5 * dpi_scale);
+        canvas.stroke_path(&curve, &paint);
+
+        let level = self.drive_level.get(cx).load(Ordering::Relaxed).min(INPUT_RANGE);
+        if level > 0.0 {
+            let mut dot = vg::Path::new();
+            dot.circle(
+                input_to_x(level),
+                output_to_y(transfer(level)),
+                DOT_RADIUS * dpi_scale,
+            );
+            canvas.fill_path(&dot, &vg::Paint::color(dot_color));
+        }
+    }
+}
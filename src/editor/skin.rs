@@ -0,0 +1,143 @@
+//! User skins. A skin is a folder inside `skins/` in the plugin's configuration directory. Its
+//! `style.css` is applied on top of the embedded stylesheet, and any of the editor's images placed
+//! next to it replace the embedded ones.
+
+use nih_plug::prelude::*;
+use nih_plug_vizia::vizia::image::{self, DynamicImage};
+use nih_plug_vizia::vizia::prelude::IntoCssStr;
+use nih_plug_vizia::vizia::style::{ParserOptions, StyleSheet};
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use crate::paths;
+
+const SKINS_FOLDER_NAME: &str = "skins";
+const SKIN_STYLESHEET_NAME: &str = "style.css";
+
+/// The images a skin can replace, along with the embedded versions.
+const IMAGES: &[(&str, &[u8])] = &[
+    ("background_image.png", include_bytes!("../resource/images/background_image.png")),
+    ("insta.png", include_bytes!("../resource/images/instagram_icon.png")),
+    ("spotify.png", include_bytes!("../resource/images/spotify_icon.png")),
+];
+
+/// Returns the folder skins are loaded from, without creating it.
+pub fn skins_dir() -> Option<PathBuf> {
+    paths::config_dir().map(|dir| dir.join(SKINS_FOLDER_NAME))
+}
+
+/// Returns the names of all skins in the skins folder, sorted alphabetically.
+pub fn available_skins() -> Vec<String> {
+    let Some(entries) = skins_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut skins: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join(SKIN_STYLESHEET_NAME).is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    skins.sort();
+
+    skins
+}
+
+/// The stylesheet of the selected skin. vizia reads every stylesheet again when the styles get
+/// reloaded, so switching skins or editing the skin's file only needs a reload. A skin that fails
+/// to parse is left out, which leaves the embedded stylesheet in charge.
+#[derive(Debug, Clone, Default)]
+pub struct SkinStyle {
+    dir: Rc<RefCell<Option<PathBuf>>>,
+}
+
+impl SkinStyle {
+    pub fn new(skin: Option<&str>) -> Self {
+        let style = Self::default();
+        style.select(skin);
+
+        style
+    }
+
+    /// Selects the skin with the given name. `None` selects the embedded look.
+    pub fn select(&self, skin: Option<&str>) {
+        *self.dir.borrow_mut() = skin.and_then(|name| skins_dir().map(|dir| dir.join(name)));
+    }
+
+    /// The most recent modification time of any file in the skin's folder. This changes whenever
+    /// the skin needs to be reloaded.
+    pub fn modified(&self) -> Option<SystemTime> {
+        let dir = self.dir.borrow().clone()?;
+
+        fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+            .max()
+    }
+
+    /// Decodes the editor's images, preferring the skin's versions over the embedded ones.
+    pub fn images(&self) -> Vec<(&'static str, DynamicImage)> {
+        let dir = self.dir.borrow().clone();
+
+        IMAGES
+            .iter()
+            .filter_map(|(name, embedded)| {
+                let skin_image = dir.as_ref().and_then(|dir| {
+                    let path = dir.join(name);
+                    if !path.is_file() {
+                        return None;
+                    }
+
+                    match image::open(&path) {
+                        Ok(img) => Some(img),
+                        Err(e) => {
+                            nih_error!("Failed to load '{}': {}", path.display(), e);
+                            None
+                        }
+                    }
+                });
+
+                match skin_image.map_or_else(|| image::load_from_memory(embedded), Ok) {
+                    Ok(img) => Some((*name, img)),
+                    Err(e) => {
+                        nih_error!("Failed to load image: {}", e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+impl IntoCssStr for SkinStyle {
+    fn get_style(&self) -> Result<String, std::io::Error> {
+        let Some(path) = self
+            .dir
+            .borrow()
+            .as_ref()
+            .map(|dir| dir.join(SKIN_STYLESHEET_NAME))
+        else {
+            return Ok(String::new());
+        };
+
+        let css = match fs::read_to_string(&path) {
+            Ok(css) => css,
+            Err(e) => {
+                nih_error!("Failed to read '{}': {}", path.display(), e);
+                return Ok(String::new());
+            }
+        };
+
+        // All stylesheets are parsed as a whole, so a single error in the skin would otherwise
+        // take the embedded styles down with it
+        let file_name = path.display().to_string();
+        if let Err(e) = StyleSheet::parse(&file_name, &css, ParserOptions::default()) {
+            nih_error!("Failed to parse '{}', using the embedded skin instead: {:?}", file_name, e);
+            return Ok(String::new());
+        }
+
+        Ok(css)
+    }
+}
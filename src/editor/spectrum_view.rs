@@ -0,0 +1,155 @@
+//! The spectrum analyzer display, with the dry input and the wet signal overlaid on a logarithmic
+//! frequency axis.
+
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use crate::host_info::HostInfo;
+use crate::spectrum::{SpectrumAnalyzer, SpectrumRing, FFT_SIZE, MIN_DB};
+
+/// The frequency range shown on the horizontal axis.
+const MIN_FREQUENCY: f32 = 20.0;
+const MAX_FREQUENCY: f32 = 20_000.0;
+/// The loudest level shown on the vertical axis.
+const MAX_DB: f32 = 6.0;
+/// Frequencies that get a grid line.
+const GRID_FREQUENCIES: [f32; 3] = [100.0, 1_000.0, 10_000.0];
+
+/// The wet signal uses the font color, the dry input uses the border color and the held peaks use
+/// the outline color. There are grid lines at 100 Hz, 1 kHz and 10 kHz.
+pub struct SpectrumView<R, S, P>
+where
+    R: Lens<Target = Arc<SpectrumRing>>,
+    S: Lens<Target = f32>,
+    P: Lens<Target = bool>,
+{
+    ring: R,
+    smoothing: S,
+    peak_hold: P,
+    host_info: Arc<HostInfo>,
+    /// The FFT runs here on the GUI thread every time the view redraws.
+    analyzer: RefCell<SpectrumAnalyzer>,
+}
+
+impl<R, S, P> SpectrumView<R, S, P>
+where
+    R: Lens<Target = Arc<SpectrumRing>>,
+    S: Lens<Target = f32>,
+    P: Lens<Target = bool>,
+{
+    pub fn new(
+        cx: &mut Context,
+        ring: R,
+        smoothing: S,
+        peak_hold: P,
+        host_info: Arc<HostInfo>,
+    ) -> Handle<Self> {
+        Self {
+            ring,
+            smoothing,
+            peak_hold,
+            host_info,
+            analyzer: RefCell::new(SpectrumAnalyzer::default()),
+        }
+        .build(cx, |_| {})
+        // New samples arrive with every processed block
+        .bind(ring.map(|ring| ring.write_pos()), |mut handle, _| handle.needs_redraw())
+    }
+}
+
+impl<R, S, P> View for SpectrumView<R, S, P>
+where
+    R: Lens<Target = Arc<SpectrumRing>>,
+    S: Lens<Target = f32>,
+    P: Lens<Target = bool>,
+{
+    fn element(&self) -> Option<&'static str> {
+        Some("spectrum-view")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let opacity = cx.opacity();
+        let color = |color: Color| {
+            let mut color: vg::Color = color.into();
+            color.set_alphaf(color.a * opacity);
+            color
+        };
+        let background_color = color(cx.background_color());
+        let wet_color = color(cx.font_color());
+        let dry_color = color(cx.border_color());
+        let peak_color = color(cx.outline_color());
+        let dpi_scale = cx.logical_to_physical(1.0).max(1.0);
+
+        let mut path = vg::Path::new();
+        path.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&path, &vg::Paint::color(background_color));
+
+        let peak_hold = self.peak_hold.get(cx);
+        let mut analyzer = self.analyzer.borrow_mut();
+        analyzer.update(&self.ring.get(cx), self.smoothing.get(cx), peak_hold);
+
+        let frequency_to_x = |frequency: f32| {
+            let fraction = (frequency / MIN_FREQUENCY).ln() / (MAX_FREQUENCY / MIN_FREQUENCY).ln();
+            bounds.x + fraction * bounds.w
+        };
+        let db_to_y = |db: f32| {
+            let fraction = (db.clamp(MIN_DB, MAX_DB) - MIN_DB) / (MAX_DB - MIN_DB);
+            bounds.bottom() - fraction * bounds.h
+        };
+
+        let mut grid = vg::Path::new();
+        for frequency in GRID_FREQUENCIES {
+            let x = frequency_to_x(frequency);
+            grid.move_to(x, bounds.top());
+            grid.line_to(x, bounds.bottom());
+        }
+        // The grid is a fainter version of the dry input's color
+        let mut grid_color = dry_color;
+        grid_color.set_alphaf(dry_color.a * 0.4);
+        let mut paint = vg::Paint::color(grid_color);
+        paint.set_line_width(0.5 * dpi_scale);
+        canvas.stroke_path(&grid, &paint);
+
+        let bin_width = self.host_info.sample_rate() / FFT_SIZE as f32;
+        let mut stroke_spectrum = |levels: &[f32], color: vg::Color, line_width: f32| {
+            let mut path = vg::Path::new();
+            let mut is_first = true;
+            // The DC bin can't be drawn on a logarithmic axis
+            for (bin, level) in levels.iter().enumerate().skip(1) {
+                let frequency = bin as f32 * bin_width;
+                if frequency < MIN_FREQUENCY {
+                    continue;
+                }
+                if frequency > MAX_FREQUENCY {
+                    break;
+                }
+
+                let (x, y) = (frequency_to_x(frequency), db_to_y(*level));
+                if is_first {
+                    path.move_to(x, y);
+                    is_first = false;
+                } else {
+                    path.line_to(x, y);
+                }
+            }
+
+            let mut paint = vg::Paint::color(color);
+            paint.set_line_width(line_width * dpi_scale);
+            canvas.stroke_path(&path, &paint);
+        };
+
+        if peak_hold {
+            stroke_spectrum(&analyzer.dry_peak_db, peak_color, 0.75);
+            stroke_spectrum(&analyzer.wet_peak_db, peak_color, 0.75);
+        }
+        stroke_spectrum(&analyzer.dry_db, dry_color, 1.0);
+        stroke_spectrum(&analyzer.wet_db, wet_color, 1.5);
+    }
+}
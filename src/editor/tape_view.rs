@@ -0,0 +1,103 @@
+//! A view of what's currently on the tape: the delay buffer's waveform with the write head and the
+//! two read heads on top.
+
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::sync::Arc;
+
+use crate::tape_overview::{TapeOverview, NUM_BINS};
+
+/// The width of the lines marking the heads.
+const HEAD_WIDTH: f32 = 2.0;
+
+/// Draws the left channel on the top half and the right channel on the bottom half. The waveform
+/// uses the font color, the write head uses the outline color and the read heads use the border
+/// color, so all of them can be styled.
+pub struct TapeView<L>
+where
+    L: Lens<Target = Arc<TapeOverview>>,
+{
+    overview: L,
+}
+
+impl<L> TapeView<L>
+where
+    L: Lens<Target = Arc<TapeOverview>>,
+{
+    pub fn new(cx: &mut Context, overview: L) -> Handle<Self> {
+        Self { overview }
+            .build(cx, |_| {})
+            // The overview changes with every processed block
+            .bind(overview.map(|overview| overview.revision()), |mut handle, _| {
+                handle.needs_redraw()
+            })
+    }
+}
+
+impl<L> View for TapeView<L>
+where
+    L: Lens<Target = Arc<TapeOverview>>,
+{
+    fn element(&self) -> Option<&'static str> {
+        Some("tape-view")
+    }
+
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let opacity = cx.opacity();
+        let color = |color: Color| {
+            let mut color: vg::Color = color.into();
+            color.set_alphaf(color.a * opacity);
+            color
+        };
+        let background_color = color(cx.background_color());
+        let wave_color = color(cx.font_color());
+        let write_head_color = color(cx.outline_color());
+        let read_head_color = color(cx.border_color());
+        let dpi_scale = cx.logical_to_physical(1.0).max(1.0);
+
+        let mut path = vg::Path::new();
+        path.rect(bounds.x, bounds.y, bounds.w, bounds.h);
+        canvas.fill_path(&path, &vg::Paint::color(background_color));
+
+        let overview = self.overview.get(cx);
+        let center_y = bounds.center().1;
+        let half_height = bounds.h / 2.0;
+        let bin_width = bounds.w / NUM_BINS as f32;
+
+        // Each bin is a single vertical line, going up for the left channel and down for the right
+        let mut wave = vg::Path::new();
+        for bin in 0..NUM_BINS {
+            let x = bounds.x + (bin as f32 + 0.5) * bin_width;
+            let peak_l = overview.peak_l(bin).min(1.0);
+            let peak_r = overview.peak_r(bin).min(1.0);
+            wave.move_to(x, center_y - peak_l * half_height);
+            wave.line_to(x, center_y + peak_r * half_height);
+        }
+        let mut paint = vg::Paint::color(wave_color);
+        paint.set_line_width(bin_width.max(dpi_scale));
+        canvas.stroke_path(&wave, &paint);
+
+        if !overview.is_active() {
+            return;
+        }
+
+        let head_x = |position: f32| bounds.x + position.clamp(0.0, 1.0) * bounds.w;
+        let mut draw_head = |x: f32, top: f32, bottom: f32, color: vg::Color| {
+            let mut path = vg::Path::new();
+            path.move_to(x, top);
+            path.line_to(x, bottom);
+            let mut paint = vg::Paint::color(color);
+            paint.set_line_width(HEAD_WIDTH * dpi_scale);
+            canvas.stroke_path(&path, &paint);
+        };
+
+        draw_head(head_x(overview.write_pos()), bounds.top(), bounds.bottom(), write_head_color);
+        draw_head(head_x(overview.read_pos_l()), bounds.top(), center_y, read_head_color);
+        draw_head(head_x(overview.read_pos_r()), center_y, bounds.bottom(), read_head_color);
+    }
+}
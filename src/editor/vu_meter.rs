@@ -0,0 +1,205 @@
+//! An analog style VU meter with a needle and an overload lamp.
+
+use nih_plug::prelude::util;
+use nih_plug_vizia::vizia::prelude::*;
+use nih_plug_vizia::vizia::vg;
+use std::cell::Cell;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::meters::ChannelLevels;
+
+/// The highest reading on the scale, in VU.
+const MAX_VU: f32 = 3.0;
+/// The marks on the scale, in VU.
+const SCALE_MARKS: [f32; 11] = [-20.0, -10.0, -7.0, -5.0, -3.0, -2.0, -1.0, 0.0, 1.0, 2.0, 3.0];
+/// The marks that get a label.
+const LABELED_MARKS: [f32; 6] = [-20.0, -10.0, -5.0, -3.0, 0.0, 3.0];
+/// How long the overload lamp stays lit after the needle hits the end of the scale.
+const OVERLOAD_HOLD_TIME: Duration = Duration::from_millis(1000);
+
+/// The needle swings this far to either side of the center, in radians.
+const HALF_SWING: f32 = 0.8;
+/// The needle's pivot sits below the face. The geometry is relative to the face's size, with the
+/// horizontal radius scaled to the width and the vertical radius scaled to the height, so the
+/// labels can be placed with percentages.
+const PIVOT_Y: f32 = 1.3;
+const SCALE_RADIUS_X: f32 = 0.45;
+const SCALE_RADIUS_Y: f32 = 1.0;
+/// The labels sit this much further out than the scale.
+const LABEL_RADIUS: f32 = 1.12;
+/// The needle starts at this fraction of the scale's radius, roughly where it enters the face.
+const NEEDLE_START_RADIUS: f32 = 0.35;
+/// The size of the boxes the labels are centered in, as a percentage of the face.
+const LABEL_SIZE_PCT: f32 = 20.0;
+
+/// A VU meter for one channel. A sine wave peaking at `reference_dbfs` reads 0 VU.
+///
+/// The meter's background color is the face. On the `vu-meter__face` element inside it, the scale
+/// uses the border color with the part above 0 VU in the outline color, and the needle uses the
+/// font color.
+pub struct VuMeter;
+
+/// The face, with the scale and the needle.
+struct VuMeterFace<L>
+where
+    L: Lens<Target = f32>,
+{
+    level_vu: L,
+}
+
+impl VuMeter {
+    pub fn new<L>(cx: &mut Context, levels: L, reference_dbfs: f32) -> Handle<'_, Self>
+    where
+        L: Lens<Target = Arc<ChannelLevels>>,
+    {
+        Self.build(cx, |cx| {
+            let level_vu =
+                levels.map(move |levels| util::gain_to_db(levels.vu()) - reference_dbfs);
+
+            VuMeterFace { level_vu }
+                .build(cx, |_| {})
+                .bind(level_vu, |mut handle, _| handle.needs_redraw())
+                .class("vu-meter__face")
+                .position_type(PositionType::SelfDirected)
+                .width(Stretch(1.0))
+                .height(Stretch(1.0));
+
+            for mark in LABELED_MARKS {
+                let (x, y) = needle_position(mark, LABEL_RADIUS);
+                let text = if mark > 0.0 { format!("+{mark}") } else { mark.to_string() };
+                Label::new(cx, &text)
+                    .class("vu-meter__label")
+                    .toggle_class("vu-meter__label--over", mark > 0.0)
+                    .position_type(PositionType::SelfDirected)
+                    .left(Percentage(x * 100.0 - LABEL_SIZE_PCT / 2.0))
+                    .top(Percentage(y * 100.0 - LABEL_SIZE_PCT / 2.0))
+                    .width(Percentage(LABEL_SIZE_PCT))
+                    .height(Percentage(LABEL_SIZE_PCT))
+                    .child_space(Stretch(1.0));
+            }
+
+            Label::new(cx, "VU")
+                .class("vu-meter__title")
+                .position_type(PositionType::SelfDirected)
+                .left(Percentage(50.0 - LABEL_SIZE_PCT / 2.0))
+                .top(Percentage(55.0))
+                .width(Percentage(LABEL_SIZE_PCT))
+                .height(Percentage(LABEL_SIZE_PCT))
+                .child_space(Stretch(1.0));
+
+            // The lamp stays lit for a moment so short overloads don't go unnoticed
+            let last_overload: Cell<Option<Instant>> = Cell::new(None);
+            let is_overloaded = level_vu.map(move |level_vu| {
+                let now = Instant::now();
+                if *level_vu >= MAX_VU {
+                    last_overload.set(Some(now));
+                }
+
+                last_overload
+                    .get()
+                    .is_some_and(|overload| now < overload + OVERLOAD_HOLD_TIME)
+            });
+            Element::new(cx)
+                .class("vu-meter__lamp")
+                .toggle_class("lit", is_overloaded)
+                .position_type(PositionType::SelfDirected)
+                .left(Stretch(1.0))
+                .right(Pixels(6.0))
+                .top(Pixels(6.0))
+                .width(Pixels(8.0))
+                .height(Pixels(8.0));
+        })
+    }
+}
+
+impl View for VuMeter {
+    fn element(&self) -> Option<&'static str> {
+        Some("vu-meter")
+    }
+}
+
+impl<L> View for VuMeterFace<L>
+where
+    L: Lens<Target = f32>,
+{
+    fn draw(&self, cx: &mut DrawContext, canvas: &mut Canvas) {
+        let bounds = cx.bounds();
+        if bounds.w == 0.0 || bounds.h == 0.0 {
+            return;
+        }
+
+        let opacity = cx.opacity();
+        let color = |color: Color| {
+            let mut color: vg::Color = color.into();
+            color.set_alphaf(color.a * opacity);
+            color
+        };
+        let scale_color = color(cx.border_color());
+        let over_color = color(cx.outline_color());
+        let needle_color = color(cx.font_color());
+        let dpi_scale = cx.logical_to_physical(1.0).max(1.0);
+
+        let to_canvas = |(x, y): (f32, f32)| (bounds.x + x * bounds.w, bounds.y + y * bounds.h);
+
+        // The arc, with the part above 0 VU drawn thicker
+        let mut stroke_arc = |from_vu: f32, to_vu: f32, color: vg::Color, line_width: f32| {
+            const STEPS: usize = 32;
+
+            let mut path = vg::Path::new();
+            for step in 0..=STEPS {
+                let vu = from_vu + (to_vu - from_vu) * (step as f32 / STEPS as f32);
+                let (x, y) = to_canvas(needle_position(vu, 1.0));
+                if step == 0 {
+                    path.move_to(x, y);
+                } else {
+                    path.line_to(x, y);
+                }
+            }
+            let mut paint = vg::Paint::color(color);
+            paint.set_line_width(line_width * dpi_scale);
+            canvas.stroke_path(&path, &paint);
+        };
+        stroke_arc(SCALE_MARKS[0], 0.0, scale_color, 1.0);
+        stroke_arc(0.0, MAX_VU, over_color, 3.0);
+
+        let mut marks = vg::Path::new();
+        for mark in SCALE_MARKS {
+            let (inner_x, inner_y) = to_canvas(needle_position(mark, 1.0));
+            let (outer_x, outer_y) = to_canvas(needle_position(mark, 1.06));
+            marks.move_to(inner_x, inner_y);
+            marks.line_to(outer_x, outer_y);
+        }
+        let mut paint = vg::Paint::color(scale_color);
+        paint.set_line_width(dpi_scale);
+        canvas.stroke_path(&marks, &paint);
+
+        // The needle rests just below the lowest mark when there's no signal
+        let level_vu = self.level_vu.get(cx);
+        let (start_x, start_y) = to_canvas(needle_position(level_vu, NEEDLE_START_RADIUS));
+        let (end_x, end_y) = to_canvas(needle_position(level_vu, 1.04));
+        let mut needle = vg::Path::new();
+        needle.move_to(start_x, start_y);
+        needle.line_to(end_x, end_y);
+        let mut paint = vg::Paint::color(needle_color);
+        paint.set_line_width(1.5 * dpi_scale);
+        canvas.stroke_path(&needle, &paint);
+    }
+}
+
+/// Where the needle points for a reading, from 0 at rest to 1 at the end of the scale, with a
+/// little room to pin past it. The scale is linear in voltage like on a real VU meter, so the
+/// marks bunch up towards the bottom.
+fn needle_fraction(vu: f32) -> f32 {
+    (util::db_to_gain(vu) / util::db_to_gain(MAX_VU)).clamp(0.0, 1.02)
+}
+
+/// The position of a reading on the face at `radius` times the scale's radius, relative to the
+/// face's size.
+fn needle_position(vu: f32, radius: f32) -> (f32, f32) {
+    let angle = (needle_fraction(vu) * 2.0 - 1.0) * HALF_SWING;
+    (
+        0.5 + angle.sin() * SCALE_RADIUS_X * radius,
+        PIVOT_Y - angle.cos() * SCALE_RADIUS_Y * radius,
+    )
+}
@@ -0,0 +1,162 @@
+//! The envelope follower, a modulation source that follows the input's level. It drives Mix,
+//! Feedback, Gain and the tone filter through its own bipolar depths, on top of the LFOs' matrix.
+//! A negative depth on Mix lets the echoes swell up once a phrase ends, a positive one on Tone
+//! opens the repeats' filter like an auto-wah.
+
+use nih_plug::prelude::*;
+
+use crate::lfo::{LfoModulation, LfoTarget};
+
+/// What the envelope follower can modulate.
+pub const ENVELOPE_TARGETS: [LfoTarget; 4] =
+    [LfoTarget::Mix, LfoTarget::Feedback, LfoTarget::Gain, LfoTarget::Tone];
+
+/// The depth parameters' IDs, in the order of [`ENVELOPE_TARGETS`].
+pub const DEPTH_IDS: [&str; 4] = ["env_to_mix", "env_to_feedback", "env_to_gain", "env_to_tone"];
+
+#[derive(Params)]
+pub struct EnvelopeParams {
+    /// How quickly the envelope rises when the input gets louder, in milliseconds.
+    #[id = "env_attack"]
+    pub attack_ms: FloatParam,
+    /// How quickly the envelope falls when the input gets quieter, in milliseconds.
+    #[id = "env_release"]
+    pub release_ms: FloatParam,
+    /// Boosts the input before it's followed. The envelope reaches its full range at the input
+    /// level of minus this many dB.
+    #[id = "env_sensitivity"]
+    pub sensitivity_db: FloatParam,
+
+    #[id = "env_to_mix"]
+    pub to_mix: FloatParam,
+    #[id = "env_to_feedback"]
+    pub to_feedback: FloatParam,
+    #[id = "env_to_gain"]
+    pub to_gain: FloatParam,
+    #[id = "env_to_tone"]
+    pub to_tone: FloatParam,
+}
+
+impl Default for EnvelopeParams {
+    fn default() -> Self {
+        let depth = |target: LfoTarget| {
+            FloatParam::new(
+                format!("Envelope > {}", target.name()),
+                0.0,
+                FloatRange::Linear { min: -1.0, max: 1.0 },
+            )
+                .with_smoother(SmoothingStyle::Linear(20.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage())
+        };
+
+        Self {
+            attack_ms: FloatParam::new(
+                "Envelope Attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 200.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+                .with_unit(" ms")
+                .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            release_ms: FloatParam::new(
+                "Envelope Release",
+                250.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 2000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+                .with_unit(" ms")
+                .with_value_to_string(formatters::v2s_f32_rounded(0)),
+            sensitivity_db: FloatParam::new(
+                "Envelope Sensitivity",
+                12.0,
+                FloatRange::Linear { min: 0.0, max: 36.0 },
+            )
+                .with_unit(" dB")
+                .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            to_mix: depth(LfoTarget::Mix),
+            to_feedback: depth(LfoTarget::Feedback),
+            to_gain: depth(LfoTarget::Gain),
+            to_tone: depth(LfoTarget::Tone),
+        }
+    }
+}
+
+impl EnvelopeParams {
+    /// The depth for `target`, if the envelope follower can modulate it.
+    pub fn depth(&self, target: LfoTarget) -> Option<&FloatParam> {
+        match target {
+            LfoTarget::Mix => Some(&self.to_mix),
+            LfoTarget::Feedback => Some(&self.to_feedback),
+            LfoTarget::Gain => Some(&self.to_gain),
+            LfoTarget::Tone => Some(&self.to_tone),
+            _ => None,
+        }
+    }
+
+    /// The depths in the order of [`ENVELOPE_TARGETS`].
+    pub fn depths(&self) -> [&FloatParam; 4] {
+        [&self.to_mix, &self.to_feedback, &self.to_gain, &self.to_tone]
+    }
+}
+
+#[derive(Default)]
+pub struct EnvelopeFollower {
+    envelope: f32,
+    /// The block's settings, see [`EnvelopeFollower::prepare()`].
+    attack_coeff: f32,
+    release_coeff: f32,
+    input_gain: f32,
+}
+
+impl EnvelopeFollower {
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+
+    /// Picks up the follower's settings at the start of a block.
+    pub fn prepare(&mut self, params: &EnvelopeParams, sample_rate: f32) {
+        self.attack_coeff = time_coeff(params.attack_ms.value(), sample_rate);
+        self.release_coeff = time_coeff(params.release_ms.value(), sample_rate);
+        self.input_gain = util::db_to_gain(params.sensitivity_db.value());
+    }
+
+    /// Follows the input by a sample and adds the envelope's modulation to `modulation`. The
+    /// envelope goes from 0 for silence to 1 for loud input, so a positive depth only ever pushes
+    /// its target up and a negative one only down.
+    #[inline]
+    pub fn next(
+        &mut self,
+        params: &EnvelopeParams,
+        input_l: f32,
+        input_r: f32,
+        modulation: &mut LfoModulation,
+    ) {
+        let level = (input_l.abs().max(input_r.abs()) * self.input_gain).min(1.0);
+        let coeff = if level > self.envelope {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.envelope = level + coeff * (self.envelope - level);
+
+        for (target, depth) in ENVELOPE_TARGETS.into_iter().zip(params.depths()) {
+            // The smoothers need to advance even when the depth is zero
+            let depth = depth.smoothed.next();
+            modulation.add(target, depth * self.envelope);
+        }
+    }
+}
+
+/// The one-pole coefficient that moves about 63% of the way to a new level in `time_ms`.
+fn time_coeff(time_ms: f32, sample_rate: f32) -> f32 {
+    (-1.0 / (time_ms * 0.001 * sample_rate)).exp()
+}
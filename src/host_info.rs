@@ -0,0 +1,45 @@
+//! Information about the host the editor needs but can't get on its own, published by the audio
+//! thread once per block.
+
+use nih_plug::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The tempo used when the host doesn't report one, same as in the audio thread.
+pub const DEFAULT_TEMPO: f32 = 120.0;
+
+pub struct HostInfo {
+    tempo: AtomicF32,
+    /// Whether the tempo comes from the host, in which case Time Sync ignores tapped tempos.
+    is_host_tempo: AtomicBool,
+    sample_rate: AtomicF32,
+}
+
+impl Default for HostInfo {
+    fn default() -> Self {
+        Self {
+            tempo: AtomicF32::new(DEFAULT_TEMPO),
+            is_host_tempo: AtomicBool::new(false),
+            sample_rate: AtomicF32::new(44100.0),
+        }
+    }
+}
+
+impl HostInfo {
+    pub fn tempo(&self) -> f32 {
+        self.tempo.load(Ordering::Relaxed)
+    }
+
+    pub fn is_host_tempo(&self) -> bool {
+        self.is_host_tempo.load(Ordering::Relaxed)
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    pub fn publish(&self, tempo: f32, is_host_tempo: bool, sample_rate: f32) {
+        self.tempo.store(tempo, Ordering::Relaxed);
+        self.is_host_tempo.store(is_host_tempo, Ordering::Relaxed);
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,432 @@
+//! The assignable LFOs and their modulation matrix. Every LFO can move Time, Feedback, Mix, Width,
+//! Noise, Crackle, Gain and the tone filter by its own bipolar depth. The modulation is applied per
+//! sample on top of the parameters' values, and doesn't change the parameters themselves.
+
+use nih_plug::prelude::*;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::{parse_sync_division, TapeParams, SYNC_DIVISIONS};
+
+pub const NUM_LFOS: usize = 2;
+pub const NUM_LFO_TARGETS: usize = 8;
+
+/// Full depth on Time doubles or halves the delay time.
+pub const TIME_RANGE: f32 = 2.0;
+/// Full depth on Tone makes the tone filter four times as bright or dark.
+pub const TONE_RANGE: f32 = 4.0;
+
+/// A synced LFO's cycle starts out as a bar long.
+const DEFAULT_DIVISION: i32 = 24;
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LfoShape {
+    #[id = "sine"]
+    Sine,
+    #[id = "triangle"]
+    Triangle,
+    #[id = "square"]
+    Square,
+    #[id = "sample-and-hold"]
+    #[name = "S&H"]
+    SampleAndHold,
+    #[id = "smooth-random"]
+    #[name = "Smooth Random"]
+    SmoothRandom,
+}
+
+/// What an LFO, or the envelope follower, can modulate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LfoTarget {
+    Time,
+    Feedback,
+    Mix,
+    Width,
+    Noise,
+    Crackle,
+    Gain,
+    Tone,
+}
+
+impl LfoTarget {
+    pub const ALL: [LfoTarget; NUM_LFO_TARGETS] = [
+        LfoTarget::Time,
+        LfoTarget::Feedback,
+        LfoTarget::Mix,
+        LfoTarget::Width,
+        LfoTarget::Noise,
+        LfoTarget::Crackle,
+        LfoTarget::Gain,
+        LfoTarget::Tone,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LfoTarget::Time => "Time",
+            LfoTarget::Feedback => "Feedback",
+            LfoTarget::Mix => "Mix",
+            LfoTarget::Width => "Width",
+            LfoTarget::Noise => "Noise",
+            LfoTarget::Crackle => "Crackle",
+            LfoTarget::Gain => "Gain",
+            LfoTarget::Tone => "Tone",
+        }
+    }
+
+    fn id(self) -> &'static str {
+        match self {
+            LfoTarget::Time => "time",
+            LfoTarget::Feedback => "feedback",
+            LfoTarget::Mix => "mix",
+            LfoTarget::Width => "width",
+            LfoTarget::Noise => "noise",
+            LfoTarget::Crackle => "crackle",
+            LfoTarget::Gain => "gain",
+            LfoTarget::Tone => "tone",
+        }
+    }
+
+    /// The parameter this target modulates. The tone filter doesn't have a parameter.
+    pub fn param(self, params: &TapeParams) -> Option<&FloatParam> {
+        match self {
+            LfoTarget::Time => Some(&params.delay_time_ms),
+            LfoTarget::Feedback => Some(&params.feedback),
+            LfoTarget::Mix => Some(&params.mix),
+            LfoTarget::Width => Some(&params.stereo_width),
+            LfoTarget::Noise => Some(&params.noise),
+            LfoTarget::Crackle => Some(&params.crackle),
+            LfoTarget::Gain => Some(&params.gain),
+            LfoTarget::Tone => None,
+        }
+    }
+
+    /// The target that modulates the parameter behind `ptr`, if any.
+    pub fn of_param(params: &TapeParams, ptr: ParamPtr) -> Option<LfoTarget> {
+        LfoTarget::ALL
+            .into_iter()
+            .find(|target| target.param(params).is_some_and(|param| param.as_ptr() == ptr))
+    }
+}
+
+#[derive(Params)]
+pub struct LfoParams {
+    #[id = "lfo_shape"]
+    pub shape: EnumParam<LfoShape>,
+    /// Follows the tempo with `division` instead of running at `rate`.
+    #[id = "lfo_sync"]
+    pub sync: BoolParam,
+    #[id = "lfo_rate"]
+    pub rate: FloatParam,
+    /// The length of a synced cycle, one of the Time Sync divisions.
+    #[id = "lfo_division"]
+    pub division: IntParam,
+    /// How far the right channel's cycle runs ahead of the left one's, in degrees.
+    #[id = "lfo_stereo_phase"]
+    pub stereo_phase: FloatParam,
+
+    #[id = "lfo_to_time"]
+    pub to_time: FloatParam,
+    #[id = "lfo_to_feedback"]
+    pub to_feedback: FloatParam,
+    #[id = "lfo_to_mix"]
+    pub to_mix: FloatParam,
+    #[id = "lfo_to_width"]
+    pub to_width: FloatParam,
+    #[id = "lfo_to_noise"]
+    pub to_noise: FloatParam,
+    #[id = "lfo_to_crackle"]
+    pub to_crackle: FloatParam,
+    #[id = "lfo_to_gain"]
+    pub to_gain: FloatParam,
+    #[id = "lfo_to_tone"]
+    pub to_tone: FloatParam,
+}
+
+impl LfoParams {
+    /// The parameters for the LFO with the given number, starting at 1.
+    pub fn new(number: usize) -> Self {
+        let depth = |target: LfoTarget| {
+            FloatParam::new(
+                format!("LFO {number} > {}", target.name()),
+                0.0,
+                FloatRange::Linear { min: -1.0, max: 1.0 },
+            )
+                .with_smoother(SmoothingStyle::Linear(20.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage())
+        };
+
+        Self {
+            shape: EnumParam::new(format!("LFO {number} Shape"), LfoShape::Sine),
+            sync: BoolParam::new(format!("LFO {number} Sync"), false),
+            rate: FloatParam::new(
+                format!("LFO {number} Rate"),
+                0.5,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+                .with_unit(" Hz")
+                .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            division: IntParam::new(
+                format!("LFO {number} Division"),
+                DEFAULT_DIVISION,
+                IntRange::Linear { min: 0, max: SYNC_DIVISIONS.len() as i32 - 1 },
+            )
+                .with_value_to_string(Arc::new(|value| SYNC_DIVISIONS[value as usize].1.to_string()))
+                .with_string_to_value(Arc::new(parse_sync_division)),
+            stereo_phase: FloatParam::new(
+                format!("LFO {number} L/R Phase"),
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 360.0 },
+            )
+                .with_unit("°")
+                .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            to_time: depth(LfoTarget::Time),
+            to_feedback: depth(LfoTarget::Feedback),
+            to_mix: depth(LfoTarget::Mix),
+            to_width: depth(LfoTarget::Width),
+            to_noise: depth(LfoTarget::Noise),
+            to_crackle: depth(LfoTarget::Crackle),
+            to_gain: depth(LfoTarget::Gain),
+            to_tone: depth(LfoTarget::Tone),
+        }
+    }
+
+    pub fn depth(&self, target: LfoTarget) -> &FloatParam {
+        match target {
+            LfoTarget::Time => &self.to_time,
+            LfoTarget::Feedback => &self.to_feedback,
+            LfoTarget::Mix => &self.to_mix,
+            LfoTarget::Width => &self.to_width,
+            LfoTarget::Noise => &self.to_noise,
+            LfoTarget::Crackle => &self.to_crackle,
+            LfoTarget::Gain => &self.to_gain,
+            LfoTarget::Tone => &self.to_tone,
+        }
+    }
+}
+
+/// The depth parameters' IDs as they appear in the parameter map, for every LFO.
+pub fn depth_ids() -> impl Iterator<Item = String> {
+    (1..=NUM_LFOS).flat_map(|number| {
+        LfoTarget::ALL
+            .into_iter()
+            .map(move |target| format!("lfo_to_{}_{number}", target.id()))
+    })
+}
+
+/// The summed modulation of all LFOs and the envelope follower for a single sample, per target and
+/// channel. The values are fractions of the parameters' ranges, see [`modulate()`] and [`scale()`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LfoModulation {
+    left: [f32; NUM_LFO_TARGETS],
+    right: [f32; NUM_LFO_TARGETS],
+}
+
+impl LfoModulation {
+    #[inline]
+    pub fn left(&self, target: LfoTarget) -> f32 {
+        self.left[target as usize]
+    }
+
+    #[inline]
+    pub fn right(&self, target: LfoTarget) -> f32 {
+        self.right[target as usize]
+    }
+
+    /// Adds the same amount to both channels.
+    #[inline]
+    pub fn add(&mut self, target: LfoTarget, amount: f32) {
+        self.left[target as usize] += amount;
+        self.right[target as usize] += amount;
+    }
+}
+
+/// Moves `plain` by `amount` of the parameter's range.
+#[inline]
+pub fn modulate(param: &FloatParam, plain: f32, amount: f32) -> f32 {
+    if amount == 0.0 {
+        return plain;
+    }
+
+    param.preview_plain((param.preview_normalized(plain) + amount).clamp(0.0, 1.0))
+}
+
+/// The factor for a target that gets scaled instead, like Time. Full depth multiplies or divides by
+/// `range`.
+#[inline]
+pub fn scale(amount: f32, range: f32) -> f32 {
+    if amount == 0.0 {
+        1.0
+    } else {
+        range.powf(amount)
+    }
+}
+
+/// The modulation at the end of the last block, for the knobs' arcs. Only updated while the
+/// editor is open.
+#[derive(Default)]
+pub struct LfoDisplay {
+    amounts: [AtomicF32; NUM_LFO_TARGETS],
+}
+
+impl LfoDisplay {
+    pub fn publish(&self, modulation: &LfoModulation) {
+        for (amount, value) in self.amounts.iter().zip(modulation.left) {
+            amount.store(value, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Where the LFOs and the envelope follower currently put the target's parameter, as a normalized
+/// value. `None` if nothing modulates the target.
+pub fn modulated_normalized(params: &TapeParams, target: LfoTarget) -> Option<f32> {
+    let param = target.param(params)?;
+    let envelope_depth = params.envelope.depth(target).map_or(0.0, |depth| depth.value());
+    if envelope_depth == 0.0 && params.lfos.iter().all(|lfo| lfo.depth(target).value() == 0.0) {
+        return None;
+    }
+
+    let amount = params.lfo_display.amounts[target as usize].load(Ordering::Relaxed);
+    Some(match target {
+        LfoTarget::Time => param.preview_normalized(param.value() * scale(amount, TIME_RANGE)),
+        _ => (param.unmodulated_normalized_value() + amount).clamp(0.0, 1.0),
+    })
+}
+
+/// The random values the random shapes move between, separately for each channel so the stereo
+/// phase works for them too.
+#[derive(Debug, Clone, Copy, Default)]
+struct RandomSteps {
+    last_phase: f32,
+    from: f32,
+    to: f32,
+}
+
+struct Lfo {
+    /// The left channel's position in the cycle, from 0 to 1.
+    phase: f32,
+    /// The block's settings, see [`LfoBank::prepare()`].
+    increment: f32,
+    shape: LfoShape,
+    stereo_offset: f32,
+    random_steps: [RandomSteps; 2],
+    rng_seed: u32,
+}
+
+impl Lfo {
+    /// Advances the LFO by a sample. Returns the left and the right channel's values, from -1 to 1.
+    #[inline]
+    fn next(&mut self) -> (f32, f32) {
+        self.phase = (self.phase + self.increment).fract();
+
+        let mut values = [0.0; 2];
+        let phases = [self.phase, (self.phase + self.stereo_offset).fract()];
+        for (channel, phase) in phases.into_iter().enumerate() {
+            let steps = &mut self.random_steps[channel];
+            if phase < steps.last_phase {
+                // A new cycle starts
+                steps.from = steps.to;
+                steps.to = crate::get_noise(&mut self.rng_seed);
+            }
+            steps.last_phase = phase;
+
+            values[channel] = match self.shape {
+                LfoShape::Sine => (std::f32::consts::TAU * phase).sin(),
+                LfoShape::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+                LfoShape::Square => {
+                    if phase < 0.5 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                LfoShape::SampleAndHold => steps.to,
+                LfoShape::SmoothRandom => {
+                    let t = 0.5 - 0.5 * (std::f32::consts::PI * phase).cos();
+                    steps.from + (steps.to - steps.from) * t
+                }
+            };
+        }
+
+        (values[0], values[1])
+    }
+}
+
+pub struct LfoBank {
+    lfos: [Lfo; NUM_LFOS],
+}
+
+impl Default for LfoBank {
+    fn default() -> Self {
+        Self {
+            // Every LFO gets its own random sequence
+            lfos: std::array::from_fn(|index| Lfo {
+                phase: 0.0,
+                increment: 0.0,
+                shape: LfoShape::Sine,
+                stereo_offset: 0.0,
+                random_steps: Default::default(),
+                rng_seed: 0x2545_f491_u32.wrapping_mul(index as u32 + 1),
+            }),
+        }
+    }
+}
+
+impl LfoBank {
+    pub fn reset(&mut self) {
+        for lfo in &mut self.lfos {
+            lfo.phase = 0.0;
+            lfo.random_steps = Default::default();
+        }
+    }
+
+    /// Picks up the LFOs' settings at the start of a block. Synced LFOs follow the song position
+    /// while the host is playing, so they line up with the bars.
+    pub fn prepare(
+        &mut self,
+        params: &[LfoParams; NUM_LFOS],
+        tempo: f32,
+        sample_rate: f32,
+        transport: &Transport,
+    ) {
+        for (lfo, params) in self.lfos.iter_mut().zip(params) {
+            lfo.shape = params.shape.value();
+            lfo.stereo_offset = params.stereo_phase.value() / 360.0;
+
+            let cycles_per_second = if params.sync.value() {
+                let beats = SYNC_DIVISIONS[params.division.value() as usize].0;
+                if let (true, Some(pos_beats)) = (transport.playing, transport.pos_beats()) {
+                    lfo.phase = (pos_beats / beats as f64).rem_euclid(1.0) as f32;
+                }
+
+                tempo / 60.0 / beats
+            } else {
+                params.rate.value()
+            };
+            lfo.increment = (cycles_per_second / sample_rate).min(0.5);
+        }
+    }
+
+    /// Advances all LFOs by a sample and sums up their modulation.
+    #[inline]
+    pub fn next(&mut self, params: &[LfoParams; NUM_LFOS]) -> LfoModulation {
+        let mut modulation = LfoModulation::default();
+        for (lfo, params) in self.lfos.iter_mut().zip(params) {
+            let (left, right) = lfo.next();
+            for target in LfoTarget::ALL {
+                // The smoothers need to advance even when the depth is zero
+                let depth = params.depth(target).smoothed.next();
+                modulation.left[target as usize] += depth * left;
+                modulation.right[target as usize] += depth * right;
+            }
+        }
+
+        modulation
+    }
+}
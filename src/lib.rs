@@ -15,21 +15,63 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use reqwest::blocking::{Client, Response}; // Add Response here
-use reqwest::Error; // Add Error here
-use std::thread;
 use nih_plug::prelude::*;
 use nih_plug_vizia::ViziaState;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32};
 use std::sync::atomic::Ordering;
-use std::sync::Arc;
-use serde::Deserialize; // Add this import
-
+use std::sync::{Arc, Mutex};
+
+use crate::diffusion::Diffuser;
+use crate::envelope::{EnvelopeFollower, EnvelopeParams};
+use crate::host_info::HostInfo;
+use crate::lfo::{LfoBank, LfoDisplay, LfoModulation, LfoParams, LfoTarget, NUM_LFOS};
+use crate::meters::{MeterLevels, MeterWriter};
+use crate::midi_learn::{CcOverrides, MidiInbox, MidiMappings};
+use crate::morph::{MorphSnapshots, MorphTargets};
+use crate::pitch_shift::PitchShifter;
+use crate::randomizer::RandomizerSettings;
+use crate::resonator::Resonator;
+use crate::snapshot::{AbSlots, ParamSnapshot};
+use crate::spectrum::SpectrumRing;
+use crate::tap_tempo::{TapTempo, TappedTime};
+use crate::tape_overview::{TapeOverview, TapeOverviewWriter};
+use crate::transport::{TapeClearFade, TransportChange, TransportOptions, TransportTracker};
+use crate::update_check::UpdateStatus;
+
+mod diffusion;
+mod echo_timeline;
 mod editor;
+mod envelope;
+mod host_info;
+mod lfo;
+mod meters;
+mod midi_learn;
+mod morph;
+mod paths;
+mod pitch_shift;
+mod presets;
+mod randomizer;
+mod resonator;
+mod settings;
+mod snapshot;
+mod spectrum;
+mod tap_tempo;
+mod tape_overview;
+mod transport;
+mod update_check;
 
 const TIME_MS_MIN: f32 = 1.0;
 const TIME_MS_MAX: f32 = 1500.0;
-const NUM_SYNC_STEPS: f32 = 18.0;
+const NUM_SYNC_STEPS: f32 = SYNC_DIVISIONS.len() as f32;
+/// The sync division of a fresh instance, a 1/8.
+const DEFAULT_DIVISION: i32 = 11;
+/// The right channel's division when the channels are first split, a dotted 1/8.
+const DEFAULT_RIGHT_DIVISION: i32 = 14;
+/// The custom division starts out as 3/16.
+const DEFAULT_CUSTOM_NUMERATOR: i32 = 3;
+const DEFAULT_CUSTOM_DENOMINATOR: i32 = 16;
+/// The rate of the tape's flutter.
+const FLUTTER_HZ: f32 = 1.5;
 
 pub struct TapeDelay {
     params: Arc<TapeParams>,
@@ -40,6 +82,22 @@ pub struct TapeDelay {
     write_pos: usize,
     sample_rate: f32,
     current_delay_samples: f32,
+    current_delay_samples_r: f32,
+
+    // With Swing, every other repeat goes through a second tape loop
+    swing_buffer_l: Vec<f32>,
+    swing_buffer_r: Vec<f32>,
+    swing_lp_state_l: f32,
+    swing_lp_state_r: f32,
+    was_swung: bool,
+
+    // The pitch shifter's heads in the feedback loop
+    pitch_shifter_l: PitchShifter,
+    pitch_shifter_r: PitchShifter,
+    // The allpass chains that blur the feedback with Diffusion
+    diffuser_l: Diffuser,
+    diffuser_r: Diffuser,
+    was_diffused: bool,
 
     // --- NEW FIELDS FOR TAPE MOJO --- //
 
@@ -61,6 +119,16 @@ pub struct TapeDelay {
     dropout_smoother: f32,
     dropout_timer: f32, // Tracks how much longer the tape stays "unplugged"
 
+    // Notes, pitch bend and glide for Resonator mode
+    resonator: Resonator,
+
+    // The assignable LFOs
+    lfo_bank: LfoBank,
+    // Follows the input's level as another modulation source
+    envelope_follower: EnvelopeFollower,
+    // Mapped CCs that are applied without the editor
+    cc_overrides: CcOverrides,
+
     // Corrosion (Erosion-style phase-modulated delay) State
     corrosion_buf_l: Vec<f32>,
     corrosion_buf_r: Vec<f32>,
@@ -74,8 +142,10 @@ pub struct TapeDelay {
 
     /// The decay factor for a single sample
     meter_decay_per_sample: f32,
-    peak_meter_l: Arc<AtomicF32>,
-    peak_meter_r: Arc<AtomicF32>,
+    meter_levels: Arc<MeterLevels>,
+    meter_writer: MeterWriter,
+    /// The level going into the tape saturation, for the editor's saturation curve
+    drive_level: Arc<AtomicF32>,
 
     crackle_integrator_l: f32,
     crackle_integrator_r: f32,
@@ -83,19 +153,80 @@ pub struct TapeDelay {
     crackle_hp_r: f32,
     was_distortion_mode: bool,
 
-    // Update check
-    pub update_available: Arc<AtomicBool>, // New field
-}
+    // Morph targets resolved from the stored snapshots, and the revision they were resolved from
+    morph_targets: Option<MorphTargets>,
+    morph_revision: u32,
 
-#[derive(Deserialize)]
-struct GitHubRelease {
-    tag_name: String,
+    // Tap tempo, timed by the number of samples processed so far
+    tap_tempo: TapTempo,
+    tap_count: u32,
+    /// The delay time the last taps set with Time Sync off, until the Time parameter moves.
+    tapped_time: Option<TappedTime>,
+    samples_processed: u64,
+
+    transport_tracker: TransportTracker,
+    tape_clear_fade: TapeClearFade,
+
+    // Decimated view of the delay buffer for the editor's tape display
+    tape_overview: Arc<TapeOverview>,
+    tape_overview_writer: TapeOverviewWriter,
+    host_info: Arc<HostInfo>,
+
+    // Dry and wet signals for the editor's spectrum analyzer
+    spectrum_ring: Arc<SpectrumRing>,
+    spectrum_write_pos: usize,
+
+    // Update check
+    pub update_status: Arc<UpdateStatus>,
 }
 
 #[derive(Params)]
 struct TapeParams {
     #[persist = "editor-state"]
     editor_state: Arc<ViziaState>,
+    /// The editor's A/B comparison slots, stored with the project so a comparison survives a
+    /// reload.
+    #[persist = "ab-slots"]
+    pub ab_slots: Mutex<AbSlots>,
+    /// The two snapshots the Morph control interpolates between.
+    #[persist = "morph-snapshots"]
+    pub morph_snapshots: Mutex<MorphSnapshots>,
+    /// Incremented by the editor whenever a morph snapshot is stored, so the audio thread knows
+    /// when to pick up the new values.
+    pub morph_revision: AtomicU32,
+    /// The randomizer's intensity, locks and ranges.
+    #[persist = "randomizer"]
+    pub randomizer: Mutex<RandomizerSettings>,
+    /// Whether the default preset from the user's settings has been applied yet. Only fresh
+    /// instances get the default preset, see [`TapeDelay::filter_state()`].
+    #[persist = "default-preset-applied"]
+    pub default_preset_applied: AtomicBool,
+    /// The CCs assigned to the parameters with MIDI learn.
+    #[persist = "midi-mappings"]
+    pub midi_mappings: Mutex<MidiMappings>,
+    /// Incoming CCs and notes, on their way from the audio thread to the editor.
+    pub midi_inbox: MidiInbox,
+    /// Incremented by the editor's Tap button, so the audio thread can time the taps.
+    pub tap_count: AtomicU32,
+    /// The MIDI note that taps the tempo, or [`midi_learn::NO_NOTE`].
+    #[persist = "tap-note"]
+    pub tap_note: AtomicI32,
+    /// The tempo set by tapping with Time Sync on, or 0 before the first taps. Time Sync follows
+    /// it when the host doesn't provide a tempo, and always in the standalone version.
+    #[persist = "tapped-tempo"]
+    pub tapped_tempo: AtomicF32,
+    /// The delay time set by tapping with Time Sync off, waiting for the editor to apply it to the
+    /// Time parameter. Negative when there's nothing to apply.
+    pub tapped_delay_ms: AtomicF32,
+    /// What happens when the host's transport starts, stops or jumps, see [`TransportOptions`].
+    #[persist = "clear-on-play"]
+    pub clear_on_play: AtomicBool,
+    #[persist = "keep-tails-on-stop"]
+    pub keep_tails_on_stop: AtomicBool,
+    #[persist = "phase-lock"]
+    pub phase_lock: AtomicBool,
+    /// The LFOs' latest modulation, for the editor.
+    pub lfo_display: LfoDisplay,
 
     #[id = "gain"]
     pub gain: FloatParam,
@@ -105,6 +236,9 @@ struct TapeParams {
     pub is_sync_active: Arc<AtomicBool>,
     #[id = "time_sync"]
     pub time_sync: BoolParam,
+    /// The note division Time Sync follows. Time keeps the free delay time.
+    #[id = "time_division"]
+    pub division: IntParam,
     #[id = "broken_tape"]
     pub broken_tape: BoolParam,
     #[id = "distortion_mode"]
@@ -120,16 +254,58 @@ struct TapeParams {
     pub crackle: FloatParam,
     #[id = "stereo_width"]
     pub stereo_width: FloatParam,
+    #[id = "morph"]
+    pub morph: FloatParam,
+    #[id = "morph_active"]
+    pub morph_active: BoolParam,
+    #[id = "morph_switch"]
+    pub morph_switch_point: FloatParam,
+    #[id = "resonator"]
+    pub resonator: BoolParam,
+    #[id = "glide"]
+    pub glide_ms: FloatParam,
+    /// Delays every other repeat. 50% keeps the repeats straight, 66.7% gives a triplet shuffle
+    /// and 75% a dotted one.
+    #[id = "swing"]
+    pub swing: FloatParam,
+    /// Gives the right channel a sync division of its own.
+    #[id = "split_divisions"]
+    pub split_divisions: BoolParam,
+    #[id = "time_right"]
+    pub division_right: IntParam,
+    /// Replaces the left channel's sync division with any fraction of a whole note.
+    #[id = "custom_division"]
+    pub custom_division: BoolParam,
+    #[id = "custom_numerator"]
+    pub custom_numerator: IntParam,
+    #[id = "custom_denominator"]
+    pub custom_denominator: IntParam,
+    /// Shifts every repeat that goes around the feedback loop, by semitones and cents.
+    #[id = "pitch"]
+    pub pitch_semitones: IntParam,
+    #[id = "pitch_fine"]
+    pub pitch_cents: FloatParam,
+    /// How much of the feedback is shifted. The rest goes around the loop at its original pitch.
+    #[id = "pitch_mix"]
+    pub pitch_mix: FloatParam,
+    #[id = "pitch_grain"]
+    pub pitch_grain_ms: FloatParam,
+    /// Blurs the repeats a little more on every pass through the feedback loop.
+    #[id = "diffusion"]
+    pub diffusion: FloatParam,
+    #[id = "diffusion_size"]
+    pub diffusion_size: FloatParam,
+    #[id = "diffusion_mod"]
+    pub diffusion_modulation: FloatParam,
+    #[nested(array, group = "LFO")]
+    pub lfos: [LfoParams; NUM_LFOS],
+    #[nested(group = "Envelope")]
+    pub envelope: EnvelopeParams,
 }
 
 impl Default for TapeParams {
     fn default() -> Self {
         let sync_default = true;
-        let is_time_sync_active = Arc::new(AtomicBool::new(sync_default));
-
-        // Clone it for the closure
-        let time_sync_flag_for_formatter = is_time_sync_active.clone();
-        let time_sync_flag_for_callback = is_time_sync_active.clone();
 
         // Create the shared memory flag
         let is_tape_broken = Arc::new(AtomicBool::new(false));
@@ -143,9 +319,42 @@ impl Default for TapeParams {
         let distortion_flag_for_feedback_formatter = is_distortion_mode.clone();
         let distortion_flag_for_mix_formatter = is_distortion_mode.clone();
 
+        // Resonator mode flag for formatters, Feedback sets the decay time in Resonator mode
+        let is_resonator = Arc::new(AtomicBool::new(false));
+        let resonator_flag_for_callback = is_resonator.clone();
+        let resonator_flag_for_time_formatter = is_resonator.clone();
+        let resonator_flag_for_division_formatter = is_resonator.clone();
+        let resonator_flag_for_feedback_formatter = is_resonator.clone();
+        let resonator_flag_for_feedback_parser = is_resonator.clone();
+        let feedback_percentage_parser = formatters::s2v_f32_percentage();
+
+        // The split and custom divisions change what the Division parameter displays
+        let sync_layout = Arc::new(SyncLayout::default());
+        let sync_layout_for_division_formatter = sync_layout.clone();
+        let sync_layout_for_split_callback = sync_layout.clone();
+        let sync_layout_for_right_callback = sync_layout.clone();
+        let sync_layout_for_custom_callback = sync_layout.clone();
+        let sync_layout_for_numerator_callback = sync_layout.clone();
+        let sync_layout_for_denominator_callback = sync_layout;
+
         Self {
             is_sync_active: is_tape_broken, // Store original in struct
             editor_state: editor::default_state(),
+            ab_slots: Mutex::new(AbSlots::default()),
+            morph_snapshots: Mutex::new(MorphSnapshots::default()),
+            morph_revision: AtomicU32::new(0),
+            randomizer: Mutex::new(RandomizerSettings::default()),
+            default_preset_applied: AtomicBool::new(false),
+            midi_mappings: Mutex::new(MidiMappings::default()),
+            midi_inbox: MidiInbox::default(),
+            tap_count: AtomicU32::new(0),
+            tap_note: AtomicI32::new(midi_learn::NO_NOTE),
+            tapped_tempo: AtomicF32::new(0.0),
+            tapped_delay_ms: AtomicF32::new(-1.0),
+            clear_on_play: AtomicBool::new(false),
+            keep_tails_on_stop: AtomicBool::new(true),
+            phase_lock: AtomicBool::new(false),
+            lfo_display: LfoDisplay::default(),
 
             gain: FloatParam::new(
                 "Gain",
@@ -165,24 +374,32 @@ impl Default for TapeParams {
             )
                 .with_smoother(SmoothingStyle::Linear(15.0))
                 .with_value_to_string(Arc::new(move |value| {
-                    // BUG FIX: Instead of checking the atomic flag, we need to know
-                    // if we are in sync mode. But the formatter closure only gives us the 'value'.
-
-                    // To fix this globally, we check the atomic, but we must ensure
-                    // the atomic is initialized correctly from the PERSISTED state.
-                    if time_sync_flag_for_formatter.load(Ordering::Relaxed) {
-                        let normalized = (value - TIME_MS_MIN) / (TIME_MS_MAX - TIME_MS_MIN);
-                        let (_, label) = get_beat_info(normalized);
-                        label.to_string()
+                    if resonator_flag_for_time_formatter.load(Ordering::Relaxed) {
+                        // The notes set the delay time in Resonator mode
+                        String::from("MIDI")
                     } else {
                         format!("{:.1} ms", value)
                     }
+                }))
+                .with_string_to_value(Arc::new(|string| {
+                    let string = string.trim();
+                    string.strip_suffix("ms").unwrap_or(string).trim().parse().ok()
                 })),
 
-            time_sync: BoolParam::new("Time Sync", sync_default)
-                .with_callback(Arc::new(move |value| {
-                    time_sync_flag_for_callback.store(value, Ordering::Relaxed);
-                })),
+            time_sync: BoolParam::new("Time Sync", sync_default),
+            division: IntParam::new(
+                "Division",
+                DEFAULT_DIVISION,
+                IntRange::Linear { min: 0, max: NUM_SYNC_STEPS as i32 - 1 },
+            )
+                .with_value_to_string(Arc::new(move |value| {
+                    if resonator_flag_for_division_formatter.load(Ordering::Relaxed) {
+                        String::from("MIDI")
+                    } else {
+                        sync_layout_for_division_formatter.label(value)
+                    }
+                }))
+                .with_string_to_value(Arc::new(parse_sync_division)),
             broken_tape: BoolParam::new("Broken", false).with_callback(Arc::new(move |value| {
                 // When user clicks button, update the flag!
                 tape_broken_flag_for_callback.store(value, Ordering::Relaxed);
@@ -199,11 +416,21 @@ impl Default for TapeParams {
                 .with_value_to_string(Arc::new(move |value| {
                     if distortion_flag_for_feedback_formatter.load(Ordering::Relaxed) {
                         String::from("0")
+                    } else if resonator_flag_for_feedback_formatter.load(Ordering::Relaxed) {
+                        format!("{:.2} s", resonator::decay_seconds(value))
                     } else {
                         format!("{:.0}", value * 100.0)
                     }
                 }))
-                .with_string_to_value(formatters::s2v_f32_percentage()),
+                .with_string_to_value(Arc::new(move |string| {
+                    if resonator_flag_for_feedback_parser.load(Ordering::Relaxed) {
+                        let string = string.trim();
+                        let seconds = string.strip_suffix('s').unwrap_or(string).trim();
+                        Some(resonator::feedback_for_decay(seconds.parse().ok()?))
+                    } else {
+                        feedback_percentage_parser(string)
+                    }
+                })),
 
             mix: FloatParam::new("Mix", 0.3, FloatRange::Linear { min: 0.0, max: 1.0 })
                 .with_smoother(SmoothingStyle::Linear(15.0))
@@ -251,6 +478,132 @@ impl Default for TapeParams {
             )
                 .with_smoother(SmoothingStyle::Linear(15.0))
                 .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            morph: FloatParam::new("Morph", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(15.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            morph_active: BoolParam::new("Morph Active", false),
+            // Toggles jump from the start to the end snapshot once Morph passes this point
+            morph_switch_point: FloatParam::new(
+                "Morph Switch",
+                0.5,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            resonator: BoolParam::new("Resonator", false).with_callback(Arc::new(move |value| {
+                resonator_flag_for_callback.store(value, Ordering::Relaxed);
+            })),
+            glide_ms: FloatParam::new(
+                "Glide",
+                0.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 2000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+                .with_unit(" ms")
+                .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            swing: FloatParam::new("Swing", 0.5, FloatRange::Linear { min: 0.5, max: 0.75 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(1))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            split_divisions: BoolParam::new("L/R Split", false).with_callback(Arc::new(
+                move |value| {
+                    sync_layout_for_split_callback.is_split.store(value, Ordering::Relaxed);
+                },
+            )),
+            division_right: IntParam::new(
+                "Right Time",
+                DEFAULT_RIGHT_DIVISION,
+                IntRange::Linear { min: 0, max: NUM_SYNC_STEPS as i32 - 1 },
+            )
+                .with_callback(Arc::new(move |value| {
+                    sync_layout_for_right_callback.right_step.store(value, Ordering::Relaxed);
+                }))
+                .with_value_to_string(Arc::new(|value| {
+                    SYNC_DIVISIONS[value as usize].1.to_string()
+                }))
+                .with_string_to_value(Arc::new(parse_sync_division)),
+            custom_division: BoolParam::new("Custom Division", false).with_callback(Arc::new(
+                move |value| {
+                    sync_layout_for_custom_callback.is_custom.store(value, Ordering::Relaxed);
+                },
+            )),
+            custom_numerator: IntParam::new(
+                "Numerator",
+                DEFAULT_CUSTOM_NUMERATOR,
+                IntRange::Linear { min: 1, max: 32 },
+            )
+                .with_callback(Arc::new(move |value| {
+                    sync_layout_for_numerator_callback.numerator.store(value, Ordering::Relaxed);
+                })),
+            custom_denominator: IntParam::new(
+                "Denominator",
+                DEFAULT_CUSTOM_DENOMINATOR,
+                IntRange::Linear { min: 1, max: 64 },
+            )
+                .with_callback(Arc::new(move |value| {
+                    sync_layout_for_denominator_callback
+                        .denominator
+                        .store(value, Ordering::Relaxed);
+                })),
+            pitch_semitones: IntParam::new("Pitch", 0, IntRange::Linear { min: -24, max: 24 })
+                .with_unit(" st"),
+            pitch_cents: FloatParam::new(
+                "Pitch Fine",
+                0.0,
+                FloatRange::Linear { min: -100.0, max: 100.0 },
+            )
+                .with_unit(" ct")
+                .with_value_to_string(formatters::v2s_f32_rounded(0)),
+            pitch_mix: FloatParam::new("Shift Mix", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            pitch_grain_ms: FloatParam::new(
+                "Grain",
+                80.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 200.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+                .with_unit(" ms")
+                .with_value_to_string(formatters::v2s_f32_rounded(0)),
+            diffusion: FloatParam::new("Diffusion", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            diffusion_size: FloatParam::new(
+                "Diffusion Size",
+                0.5,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            diffusion_modulation: FloatParam::new(
+                "Diffusion Mod",
+                0.3,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+                .with_unit(" %")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            lfos: std::array::from_fn(|index| LfoParams::new(index + 1)),
+            envelope: EnvelopeParams::default(),
         }
     }
 }
@@ -264,10 +617,21 @@ impl Default for TapeDelay {
         Self {
             params: Arc::new(TapeParams::default()),
             delay_buffer_l: Vec::new(),
+            swing_buffer_l: Vec::new(),
+            swing_buffer_r: Vec::new(),
+            swing_lp_state_l: 0.0,
+            swing_lp_state_r: 0.0,
+            was_swung: false,
+            pitch_shifter_l: PitchShifter::default(),
+            pitch_shifter_r: PitchShifter::default(),
+            diffuser_l: Diffuser::default(),
+            diffuser_r: Diffuser::default(),
+            was_diffused: false,
             delay_buffer_r: Vec::new(),
             write_pos: 0,
             sample_rate: 44100.0,
             current_delay_samples: 0.0,
+            current_delay_samples_r: 0.0,
             // Start LFO at 0
             lfo_phase: 0.0,
 
@@ -287,16 +651,34 @@ impl Default for TapeDelay {
             corrosion_bp_l: [0.0; 2],
             corrosion_bp_r: [0.0; 2],
             corrosion_rng: 0xDEAD_BEEF,
+            resonator: Resonator::default(),
+            lfo_bank: LfoBank::default(),
+            envelope_follower: EnvelopeFollower::default(),
+            cc_overrides: CcOverrides::default(),
 
             meter_decay_per_sample: 1.0,
-            peak_meter_l: Arc::new(AtomicF32::new(0.0)), // 0.0 Linear = Silence
-            peak_meter_r: Arc::new(AtomicF32::new(0.0)),
+            meter_levels: Arc::new(MeterLevels::default()),
+            meter_writer: MeterWriter::default(),
+            drive_level: Arc::new(AtomicF32::new(0.0)),
             crackle_integrator_l: 0.0,
             crackle_integrator_r: 0.0,
             crackle_hp_l: 0.0,
             crackle_hp_r: 0.0,
             was_distortion_mode: false,
-            update_available: Arc::new(AtomicBool::new(false)), // Initialize new field
+            morph_targets: None,
+            morph_revision: 0,
+            tap_tempo: TapTempo::default(),
+            tap_count: 0,
+            tapped_time: None,
+            samples_processed: 0,
+            transport_tracker: TransportTracker::default(),
+            tape_clear_fade: TapeClearFade::default(),
+            tape_overview: Arc::new(TapeOverview::default()),
+            tape_overview_writer: TapeOverviewWriter::default(),
+            host_info: Arc::new(HostInfo::default()),
+            spectrum_ring: Arc::new(SpectrumRing::default()),
+            spectrum_write_pos: 0,
+            update_status: update_check::shared_status(Self::VERSION),
         }
     }
 }
@@ -314,7 +696,7 @@ impl Plugin for TapeDelay {
         ..AudioIOLayout::const_default()
     }];
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    const MIDI_INPUT: MidiConfig = MidiConfig::MidiCCs;
     const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
 
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
@@ -326,6 +708,33 @@ impl Plugin for TapeDelay {
         self.params.clone()
     }
 
+    fn filter_state(state: &mut PluginState) {
+        // Any restored state, including state saved before the default preset existed, belongs to
+        // an instance the user has already set up
+        state
+            .fields
+            .insert(String::from("default-preset-applied"), String::from("true"));
+
+        // Swing was added together with the new sync divisions
+        if !state.params.contains_key("swing") {
+            migrate_legacy_sync_division(state);
+        }
+        migrate_snapshots(state, |snapshot| {
+            if !snapshot.values.contains_key("swing") {
+                migrate_legacy_snapshot_division(snapshot);
+            }
+        });
+        // Older sessions stored the sync division in the Time value
+        if !state.params.contains_key("time_division") {
+            migrate_division_from_time(state);
+        }
+        migrate_snapshots(state, |snapshot| {
+            if !snapshot.values.contains_key("time_division") {
+                migrate_snapshot_division_from_time(snapshot);
+            }
+        });
+    }
+
     fn initialize(
         &mut self,
         _layout: &AudioIOLayout,
@@ -336,6 +745,8 @@ impl Plugin for TapeDelay {
         let max_samples = (self.sample_rate * 2.0) as usize;
         self.delay_buffer_l = vec![0.0; max_samples];
         self.delay_buffer_r = vec![0.0; max_samples];
+        self.swing_buffer_l = vec![0.0; max_samples];
+        self.swing_buffer_r = vec![0.0; max_samples];
 
         // Resize corrosion delay buffers for the actual sample rate.
         // We need at least (base_delay + max_mod_depth) * sample_rate samples:
@@ -345,8 +756,9 @@ impl Plugin for TapeDelay {
         self.corrosion_buf_l = vec![0.0; corrosion_buf_size];
         self.corrosion_buf_r = vec![0.0; corrosion_buf_size];
         self.corrosion_write = 0;
+        self.tape_overview_writer.invalidate();
 
-        let release_db_per_second = 30.0;
+        let release_db_per_second = settings::current().meter_release_db_per_second;
 
         // Calculate the constant for 1 sample of decay
         // We store this in the struct
@@ -354,50 +766,33 @@ impl Plugin for TapeDelay {
             10.0,
             -release_db_per_second / (20.0 * _buffer_config.sample_rate),
         );
+        self.meter_writer.reset(_buffer_config.sample_rate);
+        self.resonator.reset(_buffer_config.sample_rate);
+        self.tape_clear_fade.reset(_buffer_config.sample_rate);
+        self.diffuser_l.reset(_buffer_config.sample_rate);
+        self.diffuser_r.reset(_buffer_config.sample_rate);
+        self.cc_overrides.initialize(&self.params);
 
-        // Inside your initialize function...
-        let update_available = self.update_available.clone();
-        thread::spawn(move || {
-            nih_log!("Checking for updates in the background...");
-
-            // 1. Use the blocking client so we don't need an async runtime or .await
-            let client = Client::new();
-            let res: Result<Response, Error> = client
-                .get("https://api.github.com/repos/minburg/vst-tape-delay/releases/latest")
-                .header(reqwest::header::USER_AGENT, "nih-plug-update-checker") // GitHub requires this
-                .send(); // No .await needed!
-
-            match res {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        // 2. json() is now also synchronous, so we drop the .await
-                        match response.json::<GitHubRelease>() {
-                            Ok(release) => {
-                                let latest_version_str = release.tag_name.trim_start_matches('v');
-                                // Assuming TapeDelay::VERSION is accessible here
-                                let current_version_str = TapeDelay::VERSION.trim_start_matches('v');
-
-                                if latest_version_str > current_version_str {
-                                    update_available.store(true, Ordering::Relaxed);
-                                    nih_log!("New version available: {}", release.tag_name);
-                                } else {
-                                    nih_log!("Plugin is up to date.");
-                                }
-                            }
-                            Err(e) => nih_log!("Failed to parse GitHub API response: {}", e),
-                        }
-                    } else {
-                        nih_log!("GitHub API request failed with status: {}", response.status());
-                    }
-                }
-                Err(e) => nih_log!("Failed to make GitHub API request: {}", e),
-            }
-        });
+        // Resolve the morph snapshots that may have been restored from the plugin state
+        self.morph_revision = self.params.morph_revision.load(Ordering::Acquire);
+        self.morph_targets = self.params.morph_snapshots.lock().unwrap().targets(&self.params);
+
+        // Only runs once per process, no matter how many instances get initialized
+        self.update_status.check();
 
         true
     }
 
     fn reset(&mut self) {
+        self.resonator.reset(self.sample_rate);
+        self.lfo_bank.reset();
+        self.envelope_follower.reset();
+        self.cc_overrides.reset();
+        self.pitch_shifter_l.reset();
+        self.pitch_shifter_r.reset();
+        self.diffuser_l.clear();
+        self.diffuser_r.clear();
+        self.tape_clear_fade.reset(self.sample_rate);
 
         // Clear corrosion state
         self.corrosion_buf_l.iter_mut().for_each(|s| *s = 0.0);
@@ -412,53 +807,227 @@ impl Plugin for TapeDelay {
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _ctx: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
         let sample_rate = self.sample_rate;
         let buffer_len = self.delay_buffer_l.len();
 
+        // --- MORPH ---
+        self.refresh_morph_targets();
+        let morph_targets = if self.params.morph_active.value() {
+            self.morph_targets
+        } else {
+            None
+        };
+        let morph_amt = self.params.morph.value();
+        let morph_switch_point = self.params.morph_switch_point.value();
+
+        let (is_distortion_mode, is_broken, is_time_sync, width_amt, delay_time_ms, division) =
+            match &morph_targets {
+                Some(targets) => (
+                    targets.distortion_mode.at(morph_amt, morph_switch_point),
+                    targets.broken_tape.at(morph_amt, morph_switch_point),
+                    targets.time_sync.at(morph_amt, morph_switch_point),
+                    targets.stereo_width.at(morph_amt),
+                    targets.delay_time_ms.at(morph_amt),
+                    targets.division.at(morph_amt),
+                ),
+                None => (
+                    self.params.distortion_mode.value(),
+                    self.params.broken_tape.value(),
+                    self.params.time_sync.value(),
+                    self.params.stereo_width.value(),
+                    self.params.delay_time_ms.value(),
+                    self.params.division.value(),
+                ),
+            };
+
         // --- STATE MANAGEMENT ---
-        let is_distortion_mode = self.params.distortion_mode.value();
         if self.was_distortion_mode && !is_distortion_mode {
             self.delay_buffer_l.fill(0.0);
             self.delay_buffer_r.fill(0.0);
+            self.swing_buffer_l.fill(0.0);
+            self.swing_buffer_r.fill(0.0);
+            self.tape_overview_writer.invalidate();
         }
         self.was_distortion_mode = is_distortion_mode;
 
-        let is_broken = self.params.broken_tape.value();
+        // --- TAPE OVERVIEW PREP ---
+        let is_editor_open = self.params.editor_state.is_open();
+        if is_editor_open {
+            self.tape_overview_writer.prepare(
+                &self.tape_overview,
+                &self.delay_buffer_l,
+                &self.delay_buffer_r,
+            );
+        } else {
+            // Nothing gets published while the editor is closed
+            self.tape_overview_writer.invalidate();
+        }
+        let mut last_read_pos_l = 0.0;
+        let mut last_read_pos_r = 0.0;
+
         let tape_constants = calculate_tape_constants(sample_rate, is_broken);
-        let width_amt = self.params.stereo_width.value();
 
         // --- METERING PREP ---
-        let mut max_amplitude_in_block_l: f32 = 0.0;
-        let mut max_amplitude_in_block_r: f32 = 0.0;
+        let mut max_drive_in_block: f32 = 0.0;
+
+        // --- TAP TEMPO ---
+        // Button taps are timed to the start of the block, MIDI taps to their note
+        let tap_count = self.params.tap_count.load(Ordering::Relaxed);
+        if tap_count != self.tap_count {
+            self.tap_count = tap_count;
+            self.tap(self.samples_processed);
+        }
+        let time_normalized = self.params.delay_time_ms.unmodulated_normalized_value();
+        let delay_time_ms = match self.tapped_time {
+            Some(tapped_time) if tapped_time.is_current(time_normalized) => tapped_time.delay_ms,
+            Some(_) => {
+                // Time was changed since, which also makes the tap stale for the editor
+                self.tapped_time = None;
+                self.params.tapped_delay_ms.store(-1.0, Ordering::Relaxed);
+                delay_time_ms
+            }
+            None => delay_time_ms,
+        };
+
+        // --- TRANSPORT ---
+        let transport = context.transport();
+        let transport_options = TransportOptions::load(&self.params);
+        let should_clear_tape = match self.transport_tracker.update(transport, buffer.samples()) {
+            TransportChange::Started | TransportChange::Jumped => transport_options.clear_on_play,
+            TransportChange::Stopped => !transport_options.keep_tails_on_stop,
+            TransportChange::None => false,
+        };
+        if should_clear_tape {
+            self.tape_clear_fade.start();
+        }
+        if transport_options.phase_lock && transport.playing {
+            if let Some(pos_seconds) = transport.pos_seconds() {
+                let cycles = (pos_seconds * FLUTTER_HZ as f64).rem_euclid(1.0);
+                self.lfo_phase = cycles as f32 * 2.0 * std::f32::consts::PI;
+            }
+        }
 
         // --- DELAY TIME CALCULATION (only for delay mode) ---
-        let target_delay_samples = if !is_distortion_mode {
-            let raw_target_samples = if self.params.time_sync.value() {
-                let bpm = _ctx.transport().tempo.unwrap_or(120.0) as f32;
-                let seconds_per_beat = 60.0 / bpm;
-                let current_ms = self.params.delay_time_ms.value();
-                let normalized = (current_ms - TIME_MS_MIN) / (TIME_MS_MAX - TIME_MS_MIN);
-                let (multiplier, _) = get_beat_info(normalized);
-                (seconds_per_beat * multiplier) * sample_rate
-            } else {
-                (self.params.delay_time_ms.value() / 1000.0) * sample_rate
-            };
-            let max_safe_samples = buffer_len as f32 - 100.0;
-            raw_target_samples.min(max_safe_samples)
+        // The standalone version's transport always reports its default tempo, so the tapped
+        // tempo takes precedence there
+        let tapped_tempo = self.params.tapped_tempo.load(Ordering::Relaxed);
+        let is_host_tempo =
+            transport.tempo.is_some() && context.plugin_api() != PluginApi::Standalone;
+        let tempo = match transport.tempo {
+            Some(tempo) if is_host_tempo => tempo as f32,
+            _ if tapped_tempo > 0.0 => tapped_tempo,
+            host_tempo => host_tempo.map_or(host_info::DEFAULT_TEMPO, |tempo| tempo as f32),
+        };
+        let is_resonator = self.params.resonator.value();
+        // The second tape loop only runs with Swing, so whatever it still holds from the last
+        // time is stale by now. The notes set the timing in Resonator mode.
+        let is_swung = !is_resonator
+            && (self.params.swing.value() > 0.5 || self.params.swing.smoothed.is_smoothing());
+        if is_swung && !self.was_swung {
+            self.swing_buffer_l.fill(0.0);
+            self.swing_buffer_r.fill(0.0);
+        }
+        self.was_swung = is_swung;
+        // Shifting the resonator's feedback would only detune the note
+        let is_pitch_shifted = !is_resonator
+            && (self.params.pitch_mix.value() > 0.0 || self.params.pitch_mix.smoothed.is_smoothing());
+        let pitch_ratio = pitch_shift::pitch_ratio(
+            self.params.pitch_semitones.value(),
+            self.params.pitch_cents.value(),
+        );
+        let pitch_grain_samples = self.params.pitch_grain_ms.value() * 0.001 * sample_rate;
+        // The allpass chains still hold the tail from the last time they ran. Like the pitch
+        // shifter, they would only smear the resonator's note.
+        let is_diffused = !is_resonator
+            && (self.params.diffusion.value() > 0.0 || self.params.diffusion.smoothed.is_smoothing());
+        if is_diffused && !self.was_diffused {
+            self.diffuser_l.clear();
+            self.diffuser_r.clear();
+        }
+        self.was_diffused = is_diffused;
+        let diffusion_modulation = self.params.diffusion_modulation.value();
+        let glide_coefficient =
+            resonator::glide_coefficient(self.params.glide_ms.value(), sample_rate);
+        let max_safe_samples = buffer_len as f32 - 100.0;
+        let (target_delay_samples_l, target_delay_samples_r) = if !is_distortion_mode {
+            let (seconds_l, seconds_r) =
+                delay_time_seconds(&self.params, delay_time_ms, division, is_time_sync, tempo);
+            (
+                (seconds_l * sample_rate).min(max_safe_samples),
+                (seconds_r * sample_rate).min(max_safe_samples),
+            )
         } else {
-            0.0
+            (0.0, 0.0)
         };
+        self.lfo_bank.prepare(&self.params.lfos, tempo, sample_rate, transport);
+        self.envelope_follower.prepare(&self.params.envelope, sample_rate);
+        // The editor may be holding the mappings, in which case the last ones stay in use
+        let mappings = self.params.midi_mappings.try_lock();
+        self.cc_overrides.prepare(&self.params, mappings.as_deref().ok());
+        drop(mappings);
+        let mut last_lfo_modulation = LfoModulation::default();
 
         // --- MAIN DSP LOOP ---
-        for channel_samples in buffer.iter_samples() {
+        let mut next_event = context.next_event();
+        for (sample_idx, channel_samples) in buffer.iter_samples().enumerate() {
+            // --- MIDI ---
+            while let Some(event) = next_event {
+                if event.timing() > sample_idx as u32 {
+                    break;
+                }
+
+                self.handle_midi_event(event);
+                next_event = context.next_event();
+            }
+
             // --- PER-SAMPLE PARAMETER SMOOTHING ---
-            let gain_amt = self.params.gain.smoothed.next();
-            let noise_vol = self.params.noise.smoothed.next();
-            let crackle_vol = self.params.crackle.smoothed.next();
-            let mix_amt = self.params.mix.smoothed.next();
-            let feedback_amt = self.params.feedback.smoothed.next();
+            let mut gain_amt = self.params.gain.smoothed.next();
+            let mut noise_vol = self.params.noise.smoothed.next();
+            let mut crackle_vol = self.params.crackle.smoothed.next();
+            let mut mix_amt = self.params.mix.smoothed.next();
+            let mut feedback_amt = self.params.feedback.smoothed.next();
+            // How far Swing moves every other repeat, as a fraction of the delay time
+            let swing_amt = self.params.swing.smoothed.next() * 2.0 - 1.0;
+            let pitch_mix_amt = self.params.pitch_mix.smoothed.next();
+            let diffusion_amt = self.params.diffusion.smoothed.next();
+            let diffusion_scale = diffusion::size_scale(self.params.diffusion_size.smoothed.next());
+
+            // The smoothers above still need to advance while Morph is in control
+            let morph_sample_amt = self.params.morph.smoothed.next();
+            if let Some(targets) = &morph_targets {
+                gain_amt = targets.gain.at(morph_sample_amt);
+                noise_vol = targets.noise.at(morph_sample_amt);
+                crackle_vol = targets.crackle.at(morph_sample_amt);
+                mix_amt = targets.mix.at(morph_sample_amt);
+                feedback_amt = targets.feedback.at(morph_sample_amt);
+            }
+
+            let mut samples = channel_samples.into_iter();
+            let sample_l_ref = samples.next().unwrap();
+            let sample_r_ref = samples.next().unwrap();
+            let input_l = *sample_l_ref;
+            let input_r = *sample_r_ref;
+
+            // --- LFO & ENVELOPE MODULATION ---
+            // The right channel's LFO phase applies to Time, Mix and Tone, the other targets
+            // follow the left channel
+            let mut lfo = self.lfo_bank.next(&self.params.lfos);
+            self.envelope_follower.next(&self.params.envelope, input_l, input_r, &mut lfo);
+            self.cc_overrides.next(&self.params, is_time_sync, &mut lfo);
+            last_lfo_modulation = lfo;
+            gain_amt = lfo::modulate(&self.params.gain, gain_amt, lfo.left(LfoTarget::Gain));
+            feedback_amt = lfo::modulate(&self.params.feedback, feedback_amt, lfo.left(LfoTarget::Feedback));
+            noise_vol = lfo::modulate(&self.params.noise, noise_vol, lfo.left(LfoTarget::Noise));
+            crackle_vol = lfo::modulate(&self.params.crackle, crackle_vol, lfo.left(LfoTarget::Crackle));
+            let width_amt = lfo::modulate(&self.params.stereo_width, width_amt, lfo.left(LfoTarget::Width));
+            let mix_amt_l = lfo::modulate(&self.params.mix, mix_amt, lfo.left(LfoTarget::Mix));
+            let mix_amt_r = lfo::modulate(&self.params.mix, mix_amt, lfo.right(LfoTarget::Mix));
+            let time_scale_l = lfo::scale(lfo.left(LfoTarget::Time), lfo::TIME_RANGE);
+            let time_scale_r = lfo::scale(lfo.right(LfoTarget::Time), lfo::TIME_RANGE);
+            let tone_scale_l = lfo::scale(lfo.left(LfoTarget::Tone), lfo::TONE_RANGE);
+            let tone_scale_r = lfo::scale(lfo.right(LfoTarget::Tone), lfo::TONE_RANGE);
 
             // --- GAIN COMPENSATION ---
             let (makeup_gain, compensated_noise_amt, compensated_crackle_amt) =
@@ -497,11 +1066,7 @@ impl Plugin for TapeDelay {
                 compensated_crackle_amt,
             );
 
-            let mut samples = channel_samples.into_iter();
-            let sample_l_ref = samples.next().unwrap();
-            let sample_r_ref = samples.next().unwrap();
-            let input_l = *sample_l_ref;
-            let input_r = *sample_r_ref;
+            let wet_mono;
 
             if is_distortion_mode {
                 // --- TAPE ONLY / DISTORTION MODE ---
@@ -515,15 +1080,19 @@ impl Plugin for TapeDelay {
 
                 signal_l *= vol_mod;
                 signal_r *= vol_mod;
+                max_drive_in_block = max_drive_in_block.max(signal_l.abs()).max(signal_r.abs());
 
                 let saturated_l = drive_tape_classic(gain_amt, signal_l);
                 let saturated_r = drive_tape_classic(gain_amt, signal_r);
 
-                let filtered_l = one_pole_lp(saturated_l, &mut self.lp_state_l, tape_constants.current_tone_cutoff);
-                let filtered_r = one_pole_lp(saturated_r, &mut self.lp_state_r, tape_constants.current_tone_cutoff);
+                let cutoff_l = (tape_constants.current_tone_cutoff * tone_scale_l).clamp(0.05, 0.95);
+                let cutoff_r = (tape_constants.current_tone_cutoff * tone_scale_r).clamp(0.05, 0.95);
+                let filtered_l = one_pole_lp(saturated_l, &mut self.lp_state_l, cutoff_l);
+                let filtered_r = one_pole_lp(saturated_r, &mut self.lp_state_r, cutoff_r);
 
                 *sample_l_ref = filtered_l * makeup_gain;
                 *sample_r_ref = filtered_r * makeup_gain;
+                wet_mono = (*sample_l_ref + *sample_r_ref) * 0.5;
 
             } else {
                 // --- TAPE DELAY MODE ---
@@ -533,31 +1102,131 @@ impl Plugin for TapeDelay {
                 let flutter_offset_l = self.lfo_phase.sin() * flutter_depth;
                 let flutter_offset_r = (self.lfo_phase + phase_offset_r).sin() * flutter_depth;
 
-                let smooth_coeff = 0.0005;
-                self.current_delay_samples = (self.current_delay_samples * (1.0 - smooth_coeff))
-                    + (target_delay_samples * smooth_coeff);
-
-                let spread_samples = width_amt * 0.010 * sample_rate;
-                let mod_delay_samples_l = (self.current_delay_samples - spread_samples + flutter_offset_l).max(0.0);
-                let mod_delay_samples_r = (self.current_delay_samples + spread_samples + flutter_offset_r).max(0.0);
+                let tone_spread = width_amt * 0.15;
+                let cutoff_l = ((tape_constants.current_tone_cutoff - tone_spread) * tone_scale_l).clamp(0.05, 0.95);
+                let cutoff_r = ((tape_constants.current_tone_cutoff + tone_spread) * tone_scale_r).clamp(0.05, 0.95);
+
+                let (mod_delay_samples_l, mod_delay_samples_r, input_gain, feedback_gain) =
+                    if is_resonator {
+                        // The notes set the delay time, and Feedback sets how long the resonance
+                        // rings. Flutter and stereo spread scale with the period so they stay
+                        // musical at every pitch.
+                        let (period_samples, input_gain) = self.resonator.next(glide_coefficient);
+                        self.current_delay_samples = period_samples;
+                        self.current_delay_samples_r = period_samples;
+
+                        let flutter_depth = period_samples * resonator::FLUTTER_DEPTH;
+                        let spread_samples = period_samples * width_amt * resonator::STEREO_SPREAD;
+                        let delay_l = period_samples - spread_samples
+                            + self.lfo_phase.sin() * flutter_depth
+                            - resonator::tone_filter_delay(cutoff_l);
+                        let delay_r = period_samples + spread_samples
+                            + (self.lfo_phase + phase_offset_r).sin() * flutter_depth
+                            - resonator::tone_filter_delay(cutoff_r);
+
+                        let loop_gain = resonator::loop_gain(
+                            period_samples / sample_rate,
+                            resonator::decay_seconds(feedback_amt),
+                        );
+                        // The saturation multiplies quiet signals by the gain setting
+                        (delay_l.max(1.0), delay_r.max(1.0), input_gain, loop_gain / gain_amt)
+                    } else {
+                        let smooth_coeff = 0.0005;
+                        self.current_delay_samples = (self.current_delay_samples * (1.0 - smooth_coeff))
+                            + (target_delay_samples_l * smooth_coeff);
+                        self.current_delay_samples_r = (self.current_delay_samples_r * (1.0 - smooth_coeff))
+                            + (target_delay_samples_r * smooth_coeff);
+
+                        // Swing makes the odd repeats come later, see below
+                        let spread_samples = width_amt * 0.010 * sample_rate;
+                        let swung_delay_l = self.current_delay_samples * time_scale_l * (1.0 + swing_amt);
+                        let swung_delay_r = self.current_delay_samples_r * time_scale_r * (1.0 + swing_amt);
+                        (
+                            (swung_delay_l - spread_samples + flutter_offset_l).clamp(0.0, max_safe_samples),
+                            (swung_delay_r + spread_samples + flutter_offset_r).clamp(0.0, max_safe_samples),
+                            1.0,
+                            (feedback_amt * 1.2) / gain_amt.sqrt(),
+                        )
+                    };
 
                 let read_pos_l = (self.write_pos as f32 - mod_delay_samples_l).rem_euclid(buffer_len as f32);
                 let read_pos_r = (self.write_pos as f32 - mod_delay_samples_r).rem_euclid(buffer_len as f32);
+                last_read_pos_l = read_pos_l;
+                last_read_pos_r = read_pos_r;
+
+                let (tape_gain, is_clear_fade_done) = self.tape_clear_fade.next();
+                let raw_delayed_l = linear_interpolate(&self.delay_buffer_l, read_pos_l) * tape_gain;
+                let raw_delayed_r = linear_interpolate(&self.delay_buffer_r, read_pos_r) * tape_gain;
+
+                // --- SWING ---
+                // The odd repeats play from the main tape and the even ones from a second loop,
+                // which records the main tape's repeats. The two loops feed each other, so the
+                // even repeats land right on the grid while the odd ones are pushed late.
+                let swing_delay_l = (self.current_delay_samples * time_scale_l * (1.0 - swing_amt) + flutter_offset_l).max(1.0);
+                let swing_delay_r = (self.current_delay_samples_r * time_scale_r * (1.0 - swing_amt) + flutter_offset_r).max(1.0);
+                let (feedback_source_l, feedback_source_r, swung_l, swung_r) = if is_swung {
+                    let swing_read_pos_l = (self.write_pos as f32 - swing_delay_l).rem_euclid(buffer_len as f32);
+                    let swing_read_pos_r = (self.write_pos as f32 - swing_delay_r).rem_euclid(buffer_len as f32);
+                    let swung_l = linear_interpolate(&self.swing_buffer_l, swing_read_pos_l) * tape_gain;
+                    let swung_r = linear_interpolate(&self.swing_buffer_r, swing_read_pos_r) * tape_gain;
+
+                    let repeat_l = one_pole_lp(raw_delayed_l, &mut self.swing_lp_state_l, cutoff_l);
+                    let repeat_r = one_pole_lp(raw_delayed_r, &mut self.swing_lp_state_r, cutoff_r);
+                    if let Some(buf_val) = self.swing_buffer_l.get_mut(self.write_pos) {
+                        *buf_val = drive_tape_classic(gain_amt, repeat_l * feedback_gain);
+                    }
+                    if let Some(buf_val) = self.swing_buffer_r.get_mut(self.write_pos) {
+                        *buf_val = drive_tape_classic(gain_amt, repeat_r * feedback_gain);
+                    }
 
-                let raw_delayed_l = linear_interpolate(&self.delay_buffer_l, read_pos_l);
-                let raw_delayed_r = linear_interpolate(&self.delay_buffer_r, read_pos_r);
-
-                let feedback_gain = (feedback_amt * 1.2) / gain_amt.sqrt();
-
-                let tone_spread = width_amt * 0.15;
-                let cutoff_l = (tape_constants.current_tone_cutoff - tone_spread).max(0.1);
-                let cutoff_r = (tape_constants.current_tone_cutoff + tone_spread).min(0.95);
-
-                let filtered_feedback_l = one_pole_lp(raw_delayed_l, &mut self.lp_state_l, cutoff_l);
-                let filtered_feedback_r = one_pole_lp(raw_delayed_r, &mut self.lp_state_r, cutoff_r);
-
-                let mut signal_to_record_l = input_l + (filtered_feedback_l * feedback_gain) + noise_l + crackle_l;
-                let mut signal_to_record_r = input_r + (filtered_feedback_r * feedback_gain) + noise_r + crackle_r;
+                    (swung_l, swung_r, swung_l, swung_r)
+                } else {
+                    (raw_delayed_l, raw_delayed_r, 0.0, 0.0)
+                };
+
+                // --- PITCH SHIFT ---
+                // The shifted heads read the same tape as the feedback source, so the shifted
+                // repeats go through the tone filter and the saturation like the others
+                let (feedback_source_l, feedback_source_r) = if is_pitch_shifted {
+                    let (buffer_l, buffer_r, delay_l, delay_r) = if is_swung {
+                        (&self.swing_buffer_l, &self.swing_buffer_r, swing_delay_l, swing_delay_r)
+                    } else {
+                        (&self.delay_buffer_l, &self.delay_buffer_r, mod_delay_samples_l, mod_delay_samples_r)
+                    };
+                    let shifted_l = self.pitch_shifter_l.next(buffer_l, self.write_pos, delay_l, pitch_ratio, pitch_grain_samples) * tape_gain;
+                    let shifted_r = self.pitch_shifter_r.next(buffer_r, self.write_pos, delay_r, pitch_ratio, pitch_grain_samples) * tape_gain;
+                    (
+                        feedback_source_l + (shifted_l - feedback_source_l) * pitch_mix_amt,
+                        feedback_source_r + (shifted_r - feedback_source_r) * pitch_mix_amt,
+                    )
+                } else {
+                    (feedback_source_l, feedback_source_r)
+                };
+
+                // --- DIFFUSION ---
+                // Width stretches the right channel's allpass chain and moves its modulation out
+                // of phase, so the diffused tail spreads out
+                let (feedback_source_l, feedback_source_r) = if is_diffused {
+                    let diffused_l = self.diffuser_l.process(feedback_source_l, diffusion_scale, diffusion_modulation, 0.0);
+                    let diffused_r = self.diffuser_r.process(
+                        feedback_source_r,
+                        diffusion_scale * (1.0 + width_amt * diffusion::STEREO_SPREAD),
+                        diffusion_modulation,
+                        width_amt * 0.5,
+                    );
+                    (
+                        feedback_source_l + (diffused_l - feedback_source_l) * diffusion_amt,
+                        feedback_source_r + (diffused_r - feedback_source_r) * diffusion_amt,
+                    )
+                } else {
+                    (feedback_source_l, feedback_source_r)
+                };
+
+                let filtered_feedback_l = one_pole_lp(feedback_source_l, &mut self.lp_state_l, cutoff_l);
+                let filtered_feedback_r = one_pole_lp(feedback_source_r, &mut self.lp_state_r, cutoff_r);
+
+                let mut signal_to_record_l = (input_l * input_gain) + (filtered_feedback_l * feedback_gain) + noise_l + crackle_l;
+                let mut signal_to_record_r = (input_r * input_gain) + (filtered_feedback_r * feedback_gain) + noise_r + crackle_r;
 
                 // Apply corrosion if broken
                 if is_broken {
@@ -566,6 +1235,9 @@ impl Plugin for TapeDelay {
 
                 signal_to_record_l *= vol_mod;
                 signal_to_record_r *= vol_mod;
+                max_drive_in_block = max_drive_in_block
+                    .max(signal_to_record_l.abs())
+                    .max(signal_to_record_r.abs());
 
                 let saturated_l = drive_tape_classic(gain_amt, signal_to_record_l);
                 let saturated_r = drive_tape_classic(gain_amt, signal_to_record_r);
@@ -576,24 +1248,40 @@ impl Plugin for TapeDelay {
                 if let Some(buf_val) = self.delay_buffer_r.get_mut(self.write_pos) {
                     *buf_val = saturated_r;
                 }
+                if is_clear_fade_done {
+                    self.clear_tape(self.tape_clear_fade.length());
+                }
+                if is_editor_open {
+                    self.tape_overview_writer.record(
+                        &self.tape_overview,
+                        self.write_pos,
+                        buffer_len,
+                        saturated_l,
+                        saturated_r,
+                    );
+                }
 
-                let wet_l = raw_delayed_l * makeup_gain;
-                let wet_r = raw_delayed_r * makeup_gain;
+                let wet_l = (raw_delayed_l + swung_l) * makeup_gain;
+                let wet_r = (raw_delayed_r + swung_r) * makeup_gain;
+                wet_mono = (wet_l + wet_r) * 0.5;
 
-                *sample_l_ref = (input_l * (1.0 - mix_amt)) + (wet_l * mix_amt);
-                *sample_r_ref = (input_r * (1.0 - mix_amt)) + (wet_r * mix_amt);
+                *sample_l_ref = (input_l * (1.0 - mix_amt_l)) + (wet_l * mix_amt_l);
+                *sample_r_ref = (input_r * (1.0 - mix_amt_r)) + (wet_r * mix_amt_r);
             }
 
-            // --- METERING ---
-            let abs_l = sample_l_ref.abs();
-            if abs_l > max_amplitude_in_block_l {
-                max_amplitude_in_block_l = abs_l;
-            }
-            let abs_r = sample_r_ref.abs();
-            if abs_r > max_amplitude_in_block_r {
-                max_amplitude_in_block_r = abs_r;
+            // --- SPECTRUM ---
+            if is_editor_open {
+                self.spectrum_ring.write(
+                    self.spectrum_write_pos,
+                    (input_l + input_r) * 0.5,
+                    wet_mono,
+                );
+                self.spectrum_write_pos = self.spectrum_write_pos.wrapping_add(1);
             }
 
+            // --- METERING ---
+            self.meter_writer.process(input_l, input_r, *sample_l_ref, *sample_r_ref);
+
             // --- ADVANCE WRITE HEAD (only in delay mode) ---
             if !is_distortion_mode {
                 self.write_pos = (self.write_pos + 1) % buffer_len;
@@ -601,15 +1289,25 @@ impl Plugin for TapeDelay {
         }
 
         // --- UPDATE METERS (Once per buffer block) ---
-        update_peak_meters(
-            self.params.editor_state.is_open(),
-            buffer.samples() as f32,
-            self.meter_decay_per_sample,
-            &self.peak_meter_l,
-            &self.peak_meter_r,
-            max_amplitude_in_block_l,
-            max_amplitude_in_block_r,
-        );
+        if is_editor_open {
+            self.host_info.publish(tempo, is_host_tempo, sample_rate);
+            self.params.lfo_display.publish(&last_lfo_modulation);
+            self.spectrum_ring.commit(self.spectrum_write_pos);
+            self.tape_overview_writer.publish(
+                &self.tape_overview,
+                !is_distortion_mode,
+                buffer_len,
+                self.write_pos,
+                last_read_pos_l,
+                last_read_pos_r,
+            );
+        }
+        self.samples_processed += buffer.samples() as u64;
+        let block_decay = f32::powf(self.meter_decay_per_sample, buffer.samples() as f32);
+        self.meter_writer.publish(&self.meter_levels, is_editor_open, block_decay);
+        if is_editor_open {
+            meters::update_peak(&self.drive_level, block_decay, max_drive_in_block);
+        }
 
         ProcessStatus::Normal
     }
@@ -617,15 +1315,104 @@ impl Plugin for TapeDelay {
     fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         editor::create(
             self.params.clone(),
-            self.peak_meter_l.clone(),
-            self.peak_meter_r.clone(),
+            self.meter_levels.clone(),
+            self.drive_level.clone(),
             self.params.editor_state.clone(),
-            self.update_available.clone(), // Pass the new flag to the editor
+            self.update_status.clone(),
+            self.tape_overview.clone(),
+            self.host_info.clone(),
+            self.spectrum_ring.clone(),
         )
     }
 }
 
 impl TapeDelay {
+    fn handle_midi_event(&mut self, event: PluginNoteEvent<Self>) {
+        let tap_note = self.params.tap_note.load(Ordering::Relaxed);
+        match event {
+            NoteEvent::NoteOn { timing, note, .. } if note as i32 == tap_note => {
+                self.params.midi_inbox.push_note(note);
+                self.tap(self.samples_processed + timing as u64);
+            }
+            NoteEvent::NoteOn { note, velocity, .. } => {
+                // The editor may be waiting to learn the Tap note
+                self.params.midi_inbox.push_note(note);
+                self.resonator.note_on(note, velocity);
+            }
+            NoteEvent::NoteOff { note, .. } => self.resonator.note_off(note),
+            NoteEvent::MidiPitchBend { value, .. } => self.resonator.pitch_bend(value),
+            // The editor applies the CCs to the mapped parameters, and passes them on to the host.
+            // The ones the audio thread can apply itself also work with the editor closed.
+            NoteEvent::MidiCC { cc, value, .. } => {
+                self.cc_overrides.set(&self.params, cc, value, self.sample_rate);
+                self.params.midi_inbox.push(cc, value);
+            }
+            _ => (),
+        }
+    }
+
+    /// Registers a tap at `time_samples`. With Time Sync on the taps set the tapped tempo, otherwise
+    /// the interval between them becomes the delay time right away. The editor also sets the Time
+    /// parameter to it, since only the editor can change parameters.
+    fn tap(&mut self, time_samples: u64) {
+        let Some(interval) = self
+            .tap_tempo
+            .tap(time_samples as f64 / self.sample_rate as f64)
+        else {
+            return;
+        };
+
+        if self.params.time_sync.value() {
+            self.params
+                .tapped_tempo
+                .store(tap_tempo::tempo(interval), Ordering::Relaxed);
+        } else {
+            let delay_time_ms = ((interval * 1000.0) as f32).clamp(TIME_MS_MIN, TIME_MS_MAX);
+            self.tapped_time = Some(TappedTime::new(
+                delay_time_ms,
+                self.params.delay_time_ms.unmodulated_normalized_value(),
+            ));
+            self.params.tapped_delay_ms.store(delay_time_ms, Ordering::Relaxed);
+        }
+    }
+
+    /// Clears the tape, except for the last `keep_samples` samples up to and including the write
+    /// head. Those hold the input that came in while the tape was fading out.
+    fn clear_tape(&mut self, keep_samples: usize) {
+        let len = self.delay_buffer_l.len();
+        let start = (self.write_pos + 1) % len;
+        let end = start + len - keep_samples.min(len);
+        for buf in [
+            &mut self.delay_buffer_l,
+            &mut self.delay_buffer_r,
+            &mut self.swing_buffer_l,
+            &mut self.swing_buffer_r,
+        ] {
+            if end <= len {
+                buf[start..end].fill(0.0);
+            } else {
+                buf[start..].fill(0.0);
+                buf[..end - len].fill(0.0);
+            }
+        }
+        self.diffuser_l.clear();
+        self.diffuser_r.clear();
+        self.tape_overview_writer.invalidate();
+    }
+
+    /// Picks up morph snapshots that were stored by the editor. This never blocks: if the editor
+    /// is holding the lock, the update is retried on the next block.
+    fn refresh_morph_targets(&mut self) {
+        let revision = self.params.morph_revision.load(Ordering::Acquire);
+        if revision == self.morph_revision {
+            return;
+        }
+
+        if let Ok(snapshots) = self.params.morph_snapshots.try_lock() {
+            self.morph_targets = snapshots.targets(&self.params);
+            self.morph_revision = revision;
+        }
+    }
 
     /// Fractional delay buffer reader using linear interpolation.
     /// `buf` is a circular delay buffer, `write_pos` is the current write head,
@@ -745,6 +1532,36 @@ impl TapeDelay {
 
 }
 
+/// The left and the right channel's delay time in seconds. With Time Sync on, `division` selects
+/// a note division at the given tempo, unless a custom division replaces it, and the right channel
+/// can get a division of its own.
+fn delay_time_seconds(
+    params: &TapeParams,
+    delay_time_ms: f32,
+    division: i32,
+    is_time_sync: bool,
+    tempo: f32,
+) -> (f32, f32) {
+    if is_time_sync {
+        let seconds_per_beat = 60.0 / tempo;
+        let left_beats = if params.custom_division.value() {
+            // The custom division is a fraction of a whole note
+            4.0 * params.custom_numerator.value() as f32 / params.custom_denominator.value() as f32
+        } else {
+            SYNC_DIVISIONS[division as usize].0
+        };
+        let right_beats = if params.split_divisions.value() {
+            SYNC_DIVISIONS[params.division_right.value() as usize].0
+        } else {
+            left_beats
+        };
+
+        (seconds_per_beat * left_beats, seconds_per_beat * right_beats)
+    } else {
+        (delay_time_ms / 1000.0, delay_time_ms / 1000.0)
+    }
+}
+
 pub fn normalized_to_sync_step(normalized: f32) -> i32 {
     // 1. Multiply by total steps
     let step = normalized * NUM_SYNC_STEPS;
@@ -753,37 +1570,230 @@ pub fn normalized_to_sync_step(normalized: f32) -> i32 {
     let step_i32 = step.floor() as i32;
 
     // 3. Safety Clamp: Ensure we never go out of bounds (e.g. if normalized is exactly 1.0)
-    // The valid range is 0 to 17.
+    // The valid range is 0 to NUM_SYNC_STEPS - 1.
     step_i32.clamp(0, (NUM_SYNC_STEPS as i32) - 1)
 }
 
-pub fn get_beat_info(normalized: f32) -> (f32, &'static str) {
-    // CALL THE SHARED FUNCTION HERE
-    let step_index = normalized_to_sync_step(normalized);
-
-    match step_index {
-        0 => (0.0625, "1/64"),   // Straight
-        1 => (0.125, "1/32"),    // Straight
-        2 => (0.1667, "1/16 T"), // Triplet
-        3 => (0.1875, "1/32 ."), // Dotted
-        4 => (0.25, "1/16"),
-        5 => (0.3333, "1/8 T"),
-        6 => (0.375, "1/16 ."),
-        7 => (0.5, "1/8"),
-        8 => (0.6667, "1/4 T"),
-        9 => (0.75, "1/8 ."),
-        10 => (1.0, "1/4"),
-        11 => (1.3333, "1/2 T"),
-        12 => (1.5, "1/4 ."),
-        13 => (2.0, "1/2"),
-        14 => (2.6667, "1/1 T"), // Whole Triplet
-        15 => (3.0, "1/2 ."),
-        16 => (4.0, "1 Bar"),
-        17 => (8.0, "2 Bar"),
-        _ => (1.0, "1/4"), // Should never happen thanks to clamp
+/// The note divisions Time Sync offers as (length in quarter notes, label), from short to long.
+/// "T" marks triplets, "." dotted notes, "Q" quintuplets and "S" septuplets.
+const SYNC_DIVISIONS: [(f32, &str); 26] = [
+    (0.0625, "1/64"),
+    (0.125, "1/32"),
+    (0.1429, "1/16 S"),
+    (0.1667, "1/16 T"),
+    (0.1875, "1/32 ."),
+    (0.2, "1/16 Q"),
+    (0.25, "1/16"),
+    (0.2857, "1/8 S"),
+    (0.3333, "1/8 T"),
+    (0.375, "1/16 ."),
+    (0.4, "1/8 Q"),
+    (0.5, "1/8"),
+    (0.5714, "1/4 S"),
+    (0.6667, "1/4 T"),
+    (0.75, "1/8 ."),
+    (0.8, "1/4 Q"),
+    (1.0, "1/4"),
+    (1.1429, "1/2 S"),
+    (1.3333, "1/2 T"),
+    (1.5, "1/4 ."),
+    (1.6, "1/2 Q"),
+    (2.0, "1/2"),
+    (2.6667, "1/1 T"), // Whole Triplet
+    (3.0, "1/2 ."),
+    (4.0, "1 Bar"),
+    (8.0, "2 Bar"),
+];
+
+/// The sync settings besides Division that the Division parameter's display depends on. The parameters'
+/// callbacks keep these up to date for the formatter.
+struct SyncLayout {
+    is_split: AtomicBool,
+    right_step: AtomicI32,
+    is_custom: AtomicBool,
+    numerator: AtomicI32,
+    denominator: AtomicI32,
+}
+
+impl Default for SyncLayout {
+    fn default() -> Self {
+        Self {
+            is_split: AtomicBool::new(false),
+            right_step: AtomicI32::new(DEFAULT_RIGHT_DIVISION),
+            is_custom: AtomicBool::new(false),
+            numerator: AtomicI32::new(DEFAULT_CUSTOM_NUMERATOR),
+            denominator: AtomicI32::new(DEFAULT_CUSTOM_DENOMINATOR),
+        }
+    }
+}
+
+impl SyncLayout {
+    /// The Division parameter's label for the sync step `step`. Split channels show the
+    /// right channel's division after the left one's, like "1/8 | 1/8 .".
+    fn label(&self, step: i32) -> String {
+        let left = if self.is_custom.load(Ordering::Relaxed) {
+            format!(
+                "{}/{}",
+                self.numerator.load(Ordering::Relaxed),
+                self.denominator.load(Ordering::Relaxed)
+            )
+        } else {
+            SYNC_DIVISIONS[step as usize].1.to_string()
+        };
+
+        if self.is_split.load(Ordering::Relaxed) {
+            let right_step = self.right_step.load(Ordering::Relaxed);
+            format!("{left} | {}", SYNC_DIVISIONS[right_step as usize].1)
+        } else {
+            left
+        }
+    }
+}
+
+/// Sessions saved before the quintuplet and septuplet divisions were added store the sync division
+/// as one of these 18 steps of the Time range. Moves the Time value to the same division's step in
+/// the current layout.
+fn migrate_legacy_sync_division(state: &mut PluginState) {
+    // The Time value is a plain delay time when sync is off
+    if !matches!(state.params.get("time_sync"), Some(ParamValue::Bool(true))) {
+        return;
+    }
+    if let Some(ParamValue::F32(time_ms)) = state.params.get_mut("time") {
+        let normalized = (*time_ms - TIME_MS_MIN) / (TIME_MS_MAX - TIME_MS_MIN);
+        if let Some(step) = legacy_sync_step(normalized) {
+            *time_ms = sync_step_to_ms(step);
+        }
+    }
+}
+
+/// [`migrate_legacy_sync_division()`] for a snapshot, which stores normalized values.
+fn migrate_legacy_snapshot_division(snapshot: &mut ParamSnapshot) {
+    if !snapshot.values.get("time_sync").is_some_and(|time_sync| *time_sync > 0.5) {
+        return;
+    }
+    if let Some(time) = snapshot.values.get_mut("time") {
+        if let Some(step) = legacy_sync_step(*time) {
+            *time = sync_step_to_normalized(step);
+        }
+    }
+}
+
+/// The current sync step for a normalized Time value in the old 18 step layout.
+fn legacy_sync_step(normalized: f32) -> Option<i32> {
+    const LEGACY_LABELS: [&str; 18] = [
+        "1/64", "1/32", "1/16 T", "1/32 .", "1/16", "1/8 T", "1/16 .", "1/8", "1/4 T", "1/8 .",
+        "1/4", "1/2 T", "1/4 .", "1/2", "1/1 T", "1/2 .", "1 Bar", "2 Bar",
+    ];
+
+    let legacy_step = ((normalized * LEGACY_LABELS.len() as f32).floor().max(0.0) as usize)
+        .min(LEGACY_LABELS.len() - 1);
+    SYNC_DIVISIONS
+        .iter()
+        .position(|(_, label)| *label == LEGACY_LABELS[legacy_step])
+        .map(|step| step as i32)
+}
+
+/// Applies `migrate` to every snapshot stored with the session: the A/B slots and the Morph start
+/// and end. Fields that don't parse are left alone.
+fn migrate_snapshots(state: &mut PluginState, migrate: impl Fn(&mut ParamSnapshot)) {
+    if let Some(json) = state.fields.get_mut("ab-slots") {
+        if let Ok(mut slots) = serde_json::from_str::<AbSlots>(json) {
+            [&mut slots.a, &mut slots.b].into_iter().flatten().for_each(&migrate);
+            if let Ok(migrated) = serde_json::to_string(&slots) {
+                *json = migrated;
+            }
+        }
+    }
+    if let Some(json) = state.fields.get_mut("morph-snapshots") {
+        if let Ok(mut snapshots) = serde_json::from_str::<MorphSnapshots>(json) {
+            [&mut snapshots.start, &mut snapshots.end].into_iter().flatten().for_each(&migrate);
+            if let Ok(migrated) = serde_json::to_string(&snapshots) {
+                *json = migrated;
+            }
+        }
+    }
+}
+
+/// Sessions saved before the Division parameter was added picked the sync division with the Time
+/// value. Sets Division to the step the Time value selected, so the session keeps its division.
+/// Time itself stays as it is and becomes the free delay time.
+fn migrate_division_from_time(state: &mut PluginState) {
+    if let Some(ParamValue::F32(time_ms)) = state.params.get("time") {
+        let normalized = (*time_ms - TIME_MS_MIN) / (TIME_MS_MAX - TIME_MS_MIN);
+        let step = normalized_to_sync_step(normalized);
+        state
+            .params
+            .insert(String::from("time_division"), ParamValue::I32(step));
+    }
+}
+
+/// [`migrate_division_from_time()`] for a snapshot, which stores normalized values.
+fn migrate_snapshot_division_from_time(snapshot: &mut ParamSnapshot) {
+    if let Some(time) = snapshot.values.get("time") {
+        let step = normalized_to_sync_step(*time);
+        snapshot
+            .values
+            .insert(String::from("time_division"), step as f32 / (NUM_SYNC_STEPS - 1.0));
     }
 }
 
+/// The delay time that selects a sync step, in the middle of the step's range so it's not thrown
+/// off by rounding.
+fn sync_step_to_ms(step: i32) -> f32 {
+    TIME_MS_MIN + sync_step_to_normalized(step) * (TIME_MS_MAX - TIME_MS_MIN)
+}
+
+/// [`sync_step_to_ms()`] as a normalized Time value.
+fn sync_step_to_normalized(step: i32) -> f32 {
+    (step as f32 + 0.5) / NUM_SYNC_STEPS
+}
+
+/// Parses a typed note division into the closest sync step. Accepts the labels from
+/// [`SYNC_DIVISIONS`] like "1/8 T", "1/16 ." and "2 Bar", as well as any other fraction of a
+/// whole note like "3/16". A trailing "." or "d" makes the division dotted, a "t" makes it a
+/// triplet, a "q" a quintuplet and an "s" a septuplet.
+fn parse_sync_division(string: &str) -> Option<i32> {
+    let string = string.trim().to_lowercase();
+
+    let beats = if let Some(bars) = string.strip_suffix("bars").or(string.strip_suffix("bar")) {
+        bars.trim().parse::<f32>().ok()? * 4.0
+    } else {
+        let (division, modifier) = if let Some(division) = string.strip_suffix('.') {
+            (division, 1.5)
+        } else if let Some(division) = string.strip_suffix('d') {
+            (division, 1.5)
+        } else if let Some(division) = string.strip_suffix('t') {
+            (division, 2.0 / 3.0)
+        } else if let Some(division) = string.strip_suffix('q') {
+            (division, 4.0 / 5.0)
+        } else if let Some(division) = string.strip_suffix('s') {
+            (division, 4.0 / 7.0)
+        } else {
+            (string.as_str(), 1.0)
+        };
+
+        let whole_notes = match division.split_once('/') {
+            Some((numerator, denominator)) => {
+                numerator.trim().parse::<f32>().ok()? / denominator.trim().parse::<f32>().ok()?
+            }
+            None => division.trim().parse::<f32>().ok()?,
+        };
+        whole_notes * 4.0 * modifier
+    };
+    if !beats.is_finite() || beats <= 0.0 {
+        return None;
+    }
+
+    // The steps are spaced roughly evenly on a logarithmic scale
+    (0..NUM_SYNC_STEPS as i32).min_by(|&a, &b| {
+        let distance = |step: i32| {
+            let (multiplier, _) = get_beat_info((step as f32 + 0.5) / NUM_SYNC_STEPS);
+            (multiplier / beats).ln().abs()
+        };
+        distance(a).total_cmp(&distance(b))
+    })
+}
+
 // Helper: A simple 1-pole lowpass filter (The "Tone Knob")
 // value: current sample, state: previous sample, cutoff: 0.0 to 1.0
 fn one_pole_lp(input: f32, state: &mut f32, cutoff: f32) -> f32 {
@@ -841,7 +1851,7 @@ fn get_crackle(seed: &mut u32, threshold: f32) -> f32 {
 
 #[inline]
 fn calculate_tape_constants(sample_rate: f32, is_broken: bool) -> TapeConstants {
-    let flutter_rate = 2.0 * std::f32::consts::PI * (1.5 / sample_rate);
+    let flutter_rate = 2.0 * std::f32::consts::PI * (FLUTTER_HZ / sample_rate);
     let noise_amount = 0.005;
     let crackle_amount = 0.15;
     let current_tone_cutoff = if is_broken { 0.45 } else { 0.85 };
@@ -874,6 +1884,12 @@ fn update_lfo_phase(lfo_phase: &mut f32, flutter_rate: f32) {
     }
 }
 
+/// The output gain that brings the saturated signal back to roughly the input's level.
+#[inline]
+fn makeup_gain(gain_amt: f32) -> f32 {
+    1.0 / gain_amt.powf(0.60)
+}
+
 #[inline]
 fn calculate_gain_compensation(
     gain_amt: f32,
@@ -882,7 +1898,7 @@ fn calculate_gain_compensation(
     noise_volume: f32,
     crackle_volume: f32,
 ) -> (f32, f32, f32) {
-    let makeup_gain = 1.0 / gain_amt.powf(0.60);
+    let makeup_gain = makeup_gain(gain_amt);
     let compensation_factor = gain_amt * makeup_gain;
     let compensated_noise_amt = noise_amount / compensation_factor;
     let compensated_crackle_amt = crackle_amount / compensation_factor;
@@ -978,47 +1994,6 @@ fn generate_tape_noise_and_crackle(
     (noise, crackle)
 }
 
-#[inline]
-fn update_peak_meters(
-    editor_open: bool,
-    buffer_samples: f32,
-    meter_decay_per_sample: f32,
-    peak_meter_l: &Arc<AtomicF32>,
-    peak_meter_r: &Arc<AtomicF32>,
-    max_amplitude_l: f32,
-    max_amplitude_r: f32,
-) {
-    if !editor_open {
-        return;
-    }
-
-    let block_decay = f32::powf(meter_decay_per_sample, buffer_samples);
-
-    // Update left meter
-    let current_peak_l = peak_meter_l.load(Ordering::Relaxed);
-    let mut new_peak_l = if max_amplitude_l > current_peak_l {
-        max_amplitude_l
-    } else {
-        current_peak_l * block_decay
-    };
-    if new_peak_l < 0.001 {
-        new_peak_l = 0.0;
-    }
-    peak_meter_l.store(new_peak_l, Ordering::Relaxed);
-
-    // Update right meter
-    let current_peak_r = peak_meter_r.load(Ordering::Relaxed);
-    let mut new_peak_r = if max_amplitude_r > current_peak_r {
-        max_amplitude_r
-    } else {
-        current_peak_r * block_decay
-    };
-    if new_peak_r < 0.001 {
-        new_peak_r = 0.0;
-    }
-    peak_meter_r.store(new_peak_r, Ordering::Relaxed);
-}
-
 impl Vst3Plugin for TapeDelay {
     const VST3_CLASS_ID: [u8; 16] = *b"ConvolutionDelay";
     const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] = &[
@@ -1056,3 +2031,144 @@ fn linear_interpolate(buffer: &[f32], read_pos: f32) -> f32 {
 }
 
 nih_export_vst3!(TapeDelay);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The Time value an 18 step session stored for "1/8 .".
+    const LEGACY_DOTTED_EIGHTH_MS: f32 =
+        TIME_MS_MIN + (9.5 / 18.0) * (TIME_MS_MAX - TIME_MS_MIN);
+    const DOTTED_EIGHTH_STEP: i32 = 14;
+
+    fn session(params: Vec<(&str, ParamValue)>, fields: Vec<(&str, String)>) -> PluginState {
+        PluginState {
+            version: String::from("0.1.12"),
+            params: params.into_iter().map(|(id, value)| (id.to_owned(), value)).collect(),
+            fields: fields.into_iter().map(|(id, json)| (id.to_owned(), json)).collect(),
+        }
+    }
+
+    fn snapshot(values: &[(&str, f32)]) -> ParamSnapshot {
+        ParamSnapshot {
+            values: values.iter().map(|(id, value)| (id.to_string(), *value)).collect(),
+        }
+    }
+
+    fn division_step(snapshot: &ParamSnapshot) -> i32 {
+        (snapshot.values["time_division"] * (NUM_SYNC_STEPS - 1.0)).round() as i32
+    }
+
+    #[test]
+    fn legacy_session_keeps_its_division() {
+        let mut state = session(
+            vec![
+                ("time_sync", ParamValue::Bool(true)),
+                ("time", ParamValue::F32(LEGACY_DOTTED_EIGHTH_MS)),
+            ],
+            vec![],
+        );
+        TapeDelay::filter_state(&mut state);
+
+        assert!(matches!(
+            state.params.get("time_division"),
+            Some(ParamValue::I32(DOTTED_EIGHTH_STEP))
+        ));
+    }
+
+    #[test]
+    fn legacy_division_moves_to_the_new_layout() {
+        let mut state = session(
+            vec![
+                ("time_sync", ParamValue::Bool(true)),
+                ("time", ParamValue::F32(LEGACY_DOTTED_EIGHTH_MS)),
+            ],
+            vec![],
+        );
+        migrate_legacy_sync_division(&mut state);
+
+        let Some(ParamValue::F32(time_ms)) = state.params.get("time") else {
+            panic!("Time is missing");
+        };
+        assert_eq!(*time_ms, sync_step_to_ms(DOTTED_EIGHTH_STEP));
+    }
+
+    #[test]
+    fn free_delay_time_is_not_remapped() {
+        let mut state = session(
+            vec![("time_sync", ParamValue::Bool(false)), ("time", ParamValue::F32(420.0))],
+            vec![],
+        );
+        migrate_legacy_sync_division(&mut state);
+
+        assert!(matches!(
+            state.params.get("time"),
+            Some(ParamValue::F32(time_ms)) if *time_ms == 420.0
+        ));
+    }
+
+    #[test]
+    fn division_follows_the_time_value() {
+        let mut state = session(vec![("time", ParamValue::F32(sync_step_to_ms(11)))], vec![]);
+        migrate_division_from_time(&mut state);
+
+        assert!(matches!(state.params.get("time_division"), Some(ParamValue::I32(11))));
+    }
+
+    #[test]
+    fn legacy_snapshots_keep_their_division() {
+        let legacy = snapshot(&[("time_sync", 1.0), ("time", 9.5 / 18.0)]);
+        let ab_slots = AbSlots {
+            a: Some(legacy.clone()),
+            b: Some(legacy.clone()),
+            ..AbSlots::default()
+        };
+        let morph_snapshots = MorphSnapshots {
+            start: Some(legacy.clone()),
+            end: Some(legacy),
+        };
+        let mut state = session(
+            vec![
+                ("time_sync", ParamValue::Bool(true)),
+                ("time", ParamValue::F32(LEGACY_DOTTED_EIGHTH_MS)),
+            ],
+            vec![
+                ("ab-slots", serde_json::to_string(&ab_slots).unwrap()),
+                ("morph-snapshots", serde_json::to_string(&morph_snapshots).unwrap()),
+            ],
+        );
+        TapeDelay::filter_state(&mut state);
+
+        let ab_slots: AbSlots = serde_json::from_str(&state.fields["ab-slots"]).unwrap();
+        let morph_snapshots: MorphSnapshots =
+            serde_json::from_str(&state.fields["morph-snapshots"]).unwrap();
+        for snapshot in [ab_slots.a, ab_slots.b, morph_snapshots.start, morph_snapshots.end] {
+            let snapshot = snapshot.unwrap();
+            assert_eq!(division_step(&snapshot), DOTTED_EIGHTH_STEP);
+            assert_eq!(normalized_to_sync_step(snapshot.values["time"]), DOTTED_EIGHTH_STEP);
+        }
+    }
+
+    #[test]
+    fn current_snapshots_are_left_alone() {
+        let current = snapshot(&[
+            ("swing", 0.0),
+            ("time_sync", 1.0),
+            ("time", 0.3),
+            ("time_division", 0.2),
+        ]);
+        let ab_slots = AbSlots {
+            a: Some(current.clone()),
+            ..AbSlots::default()
+        };
+        let mut state = session(
+            vec![("swing", ParamValue::F32(0.5)), ("time_division", ParamValue::I32(5))],
+            vec![("ab-slots", serde_json::to_string(&ab_slots).unwrap())],
+        );
+        TapeDelay::filter_state(&mut state);
+
+        let ab_slots: AbSlots = serde_json::from_str(&state.fields["ab-slots"]).unwrap();
+        assert_eq!(ab_slots.a, Some(current));
+        assert_eq!(ab_slots.b, None);
+    }
+}
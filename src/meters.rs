@@ -0,0 +1,138 @@
+//! Level metering for the editor. The audio thread tracks the peak, RMS and VU levels of the input
+//! and the output, and publishes them once per block.
+
+use nih_plug::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Samples at or above this level light up the clip indicator.
+const CLIP_LEVEL: f32 = 1.0;
+/// The length of the RMS window, in seconds.
+const RMS_WINDOW_SECONDS: f32 = 0.3;
+/// The time a VU meter takes to reach 99% of a steady tone's level, in seconds.
+const VU_INTEGRATION_SECONDS: f32 = 0.3;
+/// Two identical one-pole stages reach 99% of a step after this many time constants.
+const VU_TIME_CONSTANTS_TO_99_PCT: f32 = 6.64;
+/// Peaks below this are shown as silence.
+const SILENCE_LEVEL: f32 = 0.001;
+
+/// The levels of a single channel, as linear gain.
+#[derive(Default)]
+pub struct ChannelLevels {
+    peak: AtomicF32,
+    rms: AtomicF32,
+    /// The rectified level after the VU ballistics, scaled so a sine wave reads its peak level.
+    vu: AtomicF32,
+    /// Latched by the audio thread and cleared by the editor.
+    clipped: AtomicBool,
+}
+
+impl ChannelLevels {
+    pub fn peak(&self) -> f32 {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    pub fn rms(&self) -> f32 {
+        self.rms.load(Ordering::Relaxed)
+    }
+
+    pub fn vu(&self) -> f32 {
+        self.vu.load(Ordering::Relaxed)
+    }
+
+    pub fn is_clipped(&self) -> bool {
+        self.clipped.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_clip(&self) {
+        self.clipped.store(false, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+pub struct MeterLevels {
+    pub input_l: Arc<ChannelLevels>,
+    pub input_r: Arc<ChannelLevels>,
+    pub output_l: Arc<ChannelLevels>,
+    pub output_r: Arc<ChannelLevels>,
+}
+
+impl MeterLevels {
+    fn channels(&self) -> [&ChannelLevels; 4] {
+        [&*self.input_l, &*self.input_r, &*self.output_l, &*self.output_r]
+    }
+}
+
+/// The audio thread's side of the meters. The channels are in the same order as
+/// [`MeterLevels::channels()`].
+#[derive(Default)]
+pub struct MeterWriter {
+    block_peaks: [f32; 4],
+    mean_squares: [f32; 4],
+    /// The one-pole coefficient for the RMS window.
+    rms_coefficient: f32,
+    /// The VU ballistics are two cascaded one-pole lowpasses on the rectified signal, which gives
+    /// the needle its characteristic 300 ms rise without any overshoot.
+    vu_states: [[f32; 2]; 4],
+    vu_coefficient: f32,
+}
+
+impl MeterWriter {
+    pub fn reset(&mut self, sample_rate: f32) {
+        self.block_peaks = [0.0; 4];
+        self.mean_squares = [0.0; 4];
+        self.rms_coefficient = 1.0 - (-1.0 / (RMS_WINDOW_SECONDS * sample_rate)).exp();
+        self.vu_states = [[0.0; 2]; 4];
+        let vu_time_constant = VU_INTEGRATION_SECONDS / VU_TIME_CONSTANTS_TO_99_PCT;
+        self.vu_coefficient = 1.0 - (-1.0 / (vu_time_constant * sample_rate)).exp();
+    }
+
+    /// Adds a single stereo input and output sample.
+    #[inline]
+    pub fn process(&mut self, input_l: f32, input_r: f32, output_l: f32, output_r: f32) {
+        for (channel, sample) in [input_l, input_r, output_l, output_r].into_iter().enumerate() {
+            self.block_peaks[channel] = self.block_peaks[channel].max(sample.abs());
+            self.mean_squares[channel] +=
+                (sample * sample - self.mean_squares[channel]) * self.rms_coefficient;
+
+            // The average of a rectified sine wave is 2/pi times its peak
+            let rectified = sample.abs() * std::f32::consts::FRAC_PI_2;
+            let [stage_1, stage_2] = &mut self.vu_states[channel];
+            *stage_1 += (rectified - *stage_1) * self.vu_coefficient;
+            *stage_2 += (*stage_1 - *stage_2) * self.vu_coefficient;
+        }
+    }
+
+    /// Publishes the levels for the block that was just processed. Clips are always latched so
+    /// they still show up after reopening the editor, but the levels are only updated while the
+    /// editor is open.
+    pub fn publish(&mut self, levels: &MeterLevels, is_editor_open: bool, block_decay: f32) {
+        for (channel, channel_levels) in levels.channels().into_iter().enumerate() {
+            let block_peak = std::mem::take(&mut self.block_peaks[channel]);
+            if block_peak >= CLIP_LEVEL {
+                channel_levels.clipped.store(true, Ordering::Relaxed);
+            }
+
+            if is_editor_open {
+                update_peak(&channel_levels.peak, block_decay, block_peak);
+                channel_levels.rms.store(self.mean_squares[channel].sqrt(), Ordering::Relaxed);
+                channel_levels.vu.store(self.vu_states[channel][1], Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Jumps up to a new peak right away and otherwise decays by `block_decay`.
+#[inline]
+pub fn update_peak(level: &AtomicF32, block_decay: f32, block_peak: f32) {
+    let current_peak = level.load(Ordering::Relaxed);
+    let mut new_peak = if block_peak > current_peak {
+        block_peak
+    } else {
+        current_peak * block_decay
+    };
+    if new_peak < SILENCE_LEVEL {
+        new_peak = 0.0;
+    }
+    level.store(new_peak, Ordering::Relaxed);
+}
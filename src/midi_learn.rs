@@ -0,0 +1,232 @@
+//! MIDI CC control of the parameters. The audio thread passes every incoming CC on to the editor,
+//! which applies them to the mapped parameters as regular parameter changes. That way they show
+//! up in the UI, are smoothed like any other change, and get recorded by the host.
+//!
+//! The parameters the LFOs can modulate also follow their CCs on the audio thread, through the
+//! modulation path, so Time, Feedback and friends can be played with the editor closed. See
+//! [`CcOverrides`].
+
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use crate::lfo::{self, LfoModulation, LfoTarget, NUM_LFO_TARGETS};
+use crate::TapeParams;
+
+/// The number of MIDI CC controllers.
+const NUM_CCS: usize = 128;
+/// Marks a CC that hasn't changed since the editor last looked.
+const NO_VALUE: f32 = -1.0;
+/// Marks the absence of a note, both for the inbox and for the Tap note.
+pub const NO_NOTE: i32 = -1;
+/// How long the audio thread takes to follow a CC, in milliseconds. CCs only have 128 steps.
+const CC_SMOOTHING_MS: f32 = 20.0;
+/// How far a parameter may move before an override counts as taken over by other changes.
+const TAKEOVER_TOLERANCE: f32 = 1e-4;
+
+/// The CC assigned to each parameter, keyed by parameter ID. These are stored with the project.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MidiMappings {
+    pub ccs: BTreeMap<String, u8>,
+}
+
+impl MidiMappings {
+    /// Assigns `cc` to the parameter. A CC controls a single parameter, so any other parameter
+    /// that was assigned to the same CC loses its mapping.
+    pub fn learn(&mut self, param_id: &str, cc: u8) {
+        self.ccs.retain(|_, mapped_cc| *mapped_cc != cc);
+        self.ccs.insert(param_id.to_owned(), cc);
+    }
+
+    pub fn forget(&mut self, param_id: &str) {
+        self.ccs.remove(param_id);
+    }
+
+    pub fn cc(&self, param_id: &str) -> Option<u8> {
+        self.ccs.get(param_id).copied()
+    }
+
+    /// The parameter assigned to `cc`, if any.
+    pub fn param_id(&self, cc: u8) -> Option<&str> {
+        self.ccs
+            .iter()
+            .find(|(_, mapped_cc)| **mapped_cc == cc)
+            .map(|(param_id, _)| param_id.as_str())
+    }
+}
+
+/// The latest value of every CC, waiting to be picked up by the editor. Only the most recent value
+/// is kept, so a burst of CCs between two editor frames doesn't pile up. The latest note is kept
+/// the same way, for learning the Tap note.
+pub struct MidiInbox {
+    values: [AtomicF32; NUM_CCS],
+    note: AtomicI32,
+}
+
+impl Default for MidiInbox {
+    fn default() -> Self {
+        Self {
+            values: std::array::from_fn(|_| AtomicF32::new(NO_VALUE)),
+            note: AtomicI32::new(NO_NOTE),
+        }
+    }
+}
+
+impl MidiInbox {
+    /// Called by the audio thread for every incoming CC. `value` is in the `[0, 1]` range.
+    pub fn push(&self, cc: u8, value: f32) {
+        if let Some(slot) = self.values.get(cc as usize) {
+            slot.store(value.clamp(0.0, 1.0), Ordering::Relaxed);
+        }
+    }
+
+    /// Takes the CCs that changed since the last call, along with their latest values.
+    pub fn drain(&self) -> impl Iterator<Item = (u8, f32)> + '_ {
+        self.values.iter().enumerate().filter_map(|(cc, slot)| {
+            let value = slot.swap(NO_VALUE, Ordering::Relaxed);
+            (value >= 0.0).then_some((cc as u8, value))
+        })
+    }
+
+    /// Called by the audio thread for every note on.
+    pub fn push_note(&self, note: u8) {
+        self.note.store(note as i32, Ordering::Relaxed);
+    }
+
+    /// Takes the latest note that was played since the last call.
+    pub fn take_note(&self) -> Option<u8> {
+        u8::try_from(self.note.swap(NO_NOTE, Ordering::Relaxed)).ok()
+    }
+}
+
+/// A CC that holds a parameter at its position on the audio thread.
+struct CcOverride {
+    is_active: bool,
+    /// The CC's position as a normalized value of the parameter.
+    smoother: Smoother<f32>,
+    /// The parameter's own normalized value when the CC came in.
+    base: f32,
+}
+
+/// The CCs mapped to parameters the LFOs can modulate, applied on the audio thread. A CC moves its
+/// parameter by as much modulation as it takes to reach the CC's position, and holds it there until
+/// the parameter itself changes: when the editor applies the same CC, or through automation or a
+/// knob.
+pub struct CcOverrides {
+    /// The targets' parameter IDs, see [`CcOverrides::initialize()`].
+    target_ids: Vec<(String, LfoTarget)>,
+    /// The target each CC controls, following the mappings.
+    targets: [Option<LfoTarget>; NUM_CCS],
+    overrides: [CcOverride; NUM_LFO_TARGETS],
+}
+
+impl Default for CcOverrides {
+    fn default() -> Self {
+        Self {
+            target_ids: Vec::new(),
+            targets: [None; NUM_CCS],
+            overrides: std::array::from_fn(|_| CcOverride {
+                is_active: false,
+                smoother: Smoother::new(SmoothingStyle::Linear(CC_SMOOTHING_MS)),
+                base: 0.0,
+            }),
+        }
+    }
+}
+
+impl CcOverrides {
+    /// Looks up the parameter IDs of the targets, so the mappings can be matched against them
+    /// without allocating. Called when the plugin is initialized.
+    pub fn initialize(&mut self, params: &TapeParams) {
+        self.target_ids = params
+            .param_map()
+            .into_iter()
+            .filter_map(|(param_id, ptr, _)| Some((param_id, LfoTarget::of_param(params, ptr)?)))
+            .collect();
+    }
+
+    pub fn reset(&mut self) {
+        for cc_override in &mut self.overrides {
+            cc_override.is_active = false;
+        }
+    }
+
+    /// Picks up the current mappings and drops the overrides whose parameter has changed since
+    /// their CC came in. Called at the start of every block. This doesn't allocate.
+    pub fn prepare(&mut self, params: &TapeParams, mappings: Option<&MidiMappings>) {
+        if let Some(mappings) = mappings {
+            self.targets = [None; NUM_CCS];
+            for (param_id, cc) in &mappings.ccs {
+                if let Some(target) = self.targets.get_mut(*cc as usize) {
+                    *target = self
+                        .target_ids
+                        .iter()
+                        .find(|(target_id, _)| target_id == param_id)
+                        .map(|(_, target)| *target);
+                }
+            }
+        }
+
+        for target in LfoTarget::ALL {
+            let cc_override = &mut self.overrides[target as usize];
+            let Some(param) = target.param(params) else {
+                continue;
+            };
+            let moved_by = (param.unmodulated_normalized_value() - cc_override.base).abs();
+            if moved_by > TAKEOVER_TOLERANCE {
+                cc_override.is_active = false;
+            }
+        }
+    }
+
+    /// Called for every incoming CC. CCs that aren't mapped to one of the targets are left to the
+    /// editor.
+    pub fn set(&mut self, params: &TapeParams, cc: u8, value: f32, sample_rate: f32) {
+        let Some(target) = self.targets.get(cc as usize).copied().flatten() else {
+            return;
+        };
+        let Some(param) = target.param(params) else {
+            return;
+        };
+
+        let cc_override = &mut self.overrides[target as usize];
+        cc_override.base = param.unmodulated_normalized_value();
+        if !cc_override.is_active {
+            cc_override.smoother.reset(cc_override.base);
+            cc_override.is_active = true;
+        }
+        cc_override.smoother.set_target(sample_rate, value.clamp(0.0, 1.0));
+    }
+
+    /// Advances the overrides by a sample and adds them to `modulation`. With Time Sync on, the
+    /// Time parameter doesn't set the delay time, so neither does its CC.
+    #[inline]
+    pub fn next(
+        &mut self,
+        params: &TapeParams,
+        is_time_sync: bool,
+        modulation: &mut LfoModulation,
+    ) {
+        for target in LfoTarget::ALL {
+            let cc_override = &mut self.overrides[target as usize];
+            if !cc_override.is_active {
+                continue;
+            }
+            let Some(param) = target.param(params) else {
+                continue;
+            };
+
+            let position = cc_override.smoother.next();
+            let amount = match target {
+                // Time gets scaled, see `lfo::scale()`
+                LfoTarget::Time if is_time_sync => continue,
+                LfoTarget::Time => {
+                    (param.preview_plain(position) / param.value()).ln() / lfo::TIME_RANGE.ln()
+                }
+                _ => position - param.unmodulated_normalized_value(),
+            };
+            modulation.add(target, amount);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! The Morph macro control. Morph interpolates every continuous parameter between two stored
+//! snapshots, steps through the sync divisions in between, and flips toggles from the start to the
+//! end snapshot at the switch point.
+
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::snapshot::ParamSnapshot;
+use crate::TapeParams;
+
+/// The two snapshots the Morph control moves between. These are stored with the project.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MorphSnapshots {
+    pub start: Option<ParamSnapshot>,
+    pub end: Option<ParamSnapshot>,
+}
+
+impl MorphSnapshots {
+    /// Resolves the snapshots to plain values the audio thread can interpolate between. Returns
+    /// `None` until both snapshots have been stored.
+    pub(crate) fn targets(&self, params: &TapeParams) -> Option<MorphTargets> {
+        let start = self.start.as_ref()?;
+        let end = self.end.as_ref()?;
+
+        Some(MorphTargets {
+            gain: MorphRange::new(&params.gain, "gain", start, end),
+            delay_time_ms: MorphRange::new(&params.delay_time_ms, "time", start, end),
+            division: MorphStep::new(&params.division, "time_division", start, end),
+            feedback: MorphRange::new(&params.feedback, "feedback", start, end),
+            mix: MorphRange::new(&params.mix, "mix", start, end),
+            noise: MorphRange::new(&params.noise, "noise", start, end),
+            crackle: MorphRange::new(&params.crackle, "crackle", start, end),
+            stereo_width: MorphRange::new(&params.stereo_width, "stereo_width", start, end),
+            time_sync: MorphSwitch::new(&params.time_sync, "time_sync", start, end),
+            broken_tape: MorphSwitch::new(&params.broken_tape, "broken_tape", start, end),
+            distortion_mode: MorphSwitch::new(
+                &params.distortion_mode,
+                "distortion_mode",
+                start,
+                end,
+            ),
+        })
+    }
+}
+
+/// Plain start and end values for every parameter the Morph control moves.
+#[derive(Debug, Clone, Copy)]
+pub struct MorphTargets {
+    pub gain: MorphRange,
+    pub delay_time_ms: MorphRange,
+    pub division: MorphStep,
+    pub feedback: MorphRange,
+    pub mix: MorphRange,
+    pub noise: MorphRange,
+    pub crackle: MorphRange,
+    pub stereo_width: MorphRange,
+    pub time_sync: MorphSwitch,
+    pub broken_tape: MorphSwitch,
+    pub distortion_mode: MorphSwitch,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MorphRange {
+    start: f32,
+    end: f32,
+}
+
+impl MorphRange {
+    fn new(param: &FloatParam, id: &str, start: &ParamSnapshot, end: &ParamSnapshot) -> Self {
+        Self {
+            start: param.preview_plain(snapshot_normalized_value(param, id, start)),
+            end: param.preview_plain(snapshot_normalized_value(param, id, end)),
+        }
+    }
+
+    #[inline]
+    pub fn at(&self, amount: f32) -> f32 {
+        self.start + (self.end - self.start) * amount
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MorphStep {
+    start: i32,
+    end: i32,
+}
+
+impl MorphStep {
+    fn new(param: &IntParam, id: &str, start: &ParamSnapshot, end: &ParamSnapshot) -> Self {
+        Self {
+            start: param.preview_plain(snapshot_normalized_value(param, id, start)),
+            end: param.preview_plain(snapshot_normalized_value(param, id, end)),
+        }
+    }
+
+    #[inline]
+    pub fn at(&self, amount: f32) -> i32 {
+        (self.start as f32 + (self.end - self.start) as f32 * amount).round() as i32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MorphSwitch {
+    start: bool,
+    end: bool,
+}
+
+impl MorphSwitch {
+    fn new(param: &BoolParam, id: &str, start: &ParamSnapshot, end: &ParamSnapshot) -> Self {
+        Self {
+            start: snapshot_normalized_value(param, id, start) >= 0.5,
+            end: snapshot_normalized_value(param, id, end) >= 0.5,
+        }
+    }
+
+    #[inline]
+    pub fn at(&self, amount: f32, switch_point: f32) -> bool {
+        if amount >= switch_point {
+            self.end
+        } else {
+            self.start
+        }
+    }
+}
+
+/// Looks up the parameter with the given ID in the snapshot. Parameters that are missing from the
+/// snapshot keep their current value.
+fn snapshot_normalized_value<P: Param>(param: &P, id: &str, snapshot: &ParamSnapshot) -> f32 {
+    snapshot
+        .values
+        .get(id)
+        .copied()
+        .unwrap_or_else(|| param.unmodulated_normalized_value())
+}
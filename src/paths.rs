@@ -0,0 +1,27 @@
+//! Locations of the files the plugin keeps outside of the host's project state.
+
+use std::env;
+use std::path::PathBuf;
+
+/// The name of the plugin's folder inside the platform's configuration directory.
+const CONFIG_FOLDER_NAME: &str = "Convolution Tape Delay";
+
+/// Returns the plugin's configuration directory, without creating it. This is
+/// `%APPDATA%\Convolution Tape Delay` on Windows,
+/// `~/Library/Application Support/Convolution Tape Delay` on macOS, and
+/// `$XDG_CONFIG_HOME/Convolution Tape Delay` (falling back to `~/.config`) everywhere else.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }?;
+
+    Some(base.join(CONFIG_FOLDER_NAME))
+}
@@ -0,0 +1,53 @@
+//! The pitch shifter in the feedback loop. Two extra playback heads read the tape around the
+//! regular head's position while they drift towards or away from the record head, which plays the
+//! repeats back faster or slower. The heads take turns, each fading in and out over a grain, so
+//! the jump back to the start of a grain can't be heard. Every repeat that goes around the loop
+//! gets shifted again, so the echoes climb or fall in steps.
+
+use crate::linear_interpolate;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PitchShifter {
+    /// How far the first head is through its grain, from 0 to 1. The second head is half a grain
+    /// further.
+    phase: f32,
+}
+
+impl PitchShifter {
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+
+    /// Advances the heads by a sample and reads `buffer` at `ratio` times the tape speed. The heads
+    /// are centered around `delay_samples` behind `write_pos`, so the shifted repeats stay on the
+    /// beat. Grains longer than the delay time are shortened to fit.
+    #[inline]
+    pub fn next(
+        &mut self,
+        buffer: &[f32],
+        write_pos: usize,
+        delay_samples: f32,
+        ratio: f32,
+        grain_samples: f32,
+    ) -> f32 {
+        let grain_samples = grain_samples.min(2.0 * (delay_samples - 1.0)).max(1.0);
+        self.phase = (self.phase + (1.0 - ratio) / grain_samples).rem_euclid(1.0);
+
+        let len = buffer.len() as f32;
+        let mut output = 0.0;
+        for head_phase in [self.phase, (self.phase + 0.5).fract()] {
+            let head_delay = (delay_samples + (head_phase - 0.5) * grain_samples).clamp(1.0, len - 2.0);
+            let read_pos = (write_pos as f32 - head_delay).rem_euclid(len);
+            // The two Hann windows add up to 1 at every point
+            let window = (std::f32::consts::PI * head_phase).sin().powi(2);
+            output += linear_interpolate(buffer, read_pos) * window;
+        }
+
+        output
+    }
+}
+
+/// The playback speed for a shift by `semitones` and `cents`.
+pub fn pitch_ratio(semitones: i32, cents: f32) -> f32 {
+    2.0_f32.powf((semitones as f32 + cents / 100.0) / 12.0)
+}
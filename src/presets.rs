@@ -0,0 +1,70 @@
+//! The factory preset bank. The bank is compiled into the binary, just like the fonts and images
+//! used by the editor, so it is available on a fresh install without any extra files.
+
+use nih_plug::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::TapeParams;
+
+const FACTORY_BANK_JSON: &str = include_str!("resource/presets/factory_bank.json");
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    /// Plain parameter values keyed by parameter ID. Parameters that are missing from this map are
+    /// reset to their default values when the preset gets loaded.
+    #[serde(default)]
+    pub params: BTreeMap<String, PresetValue>,
+}
+
+/// A single value in a preset. Toggles can be written as JSON booleans, everything else uses the
+/// parameter's plain value.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum PresetValue {
+    Bool(bool),
+    Plain(f32),
+}
+
+/// Returns the embedded factory bank. The bank is parsed once on first use.
+pub fn factory_bank() -> &'static [Preset] {
+    static BANK: OnceLock<Vec<Preset>> = OnceLock::new();
+
+    BANK.get_or_init(|| match serde_json::from_str(FACTORY_BANK_JSON) {
+        Ok(bank) => bank,
+        Err(e) => {
+            nih_error!("Failed to parse the factory preset bank: {}", e);
+            Vec::new()
+        }
+    })
+}
+
+impl Preset {
+    /// Resolves this preset to a normalized value for every parameter in `params`.
+    pub(crate) fn normalized_values(&self, params: &TapeParams) -> Vec<(ParamPtr, f32)> {
+        params
+            .param_map()
+            .into_iter()
+            .map(|(id, ptr, _)| {
+                // SAFETY: The pointers come straight from `params`, which outlives this function
+                let normalized = unsafe {
+                    match self.params.get(&id) {
+                        Some(PresetValue::Bool(value)) => {
+                            ptr.preview_normalized(if *value { 1.0 } else { 0.0 })
+                        }
+                        Some(PresetValue::Plain(value)) => ptr.preview_normalized(*value),
+                        None => ptr.default_normalized_value(),
+                    }
+                };
+
+                (ptr, normalized)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,148 @@
+//! A constrained randomizer for happy accidents. New values stay within a user-defined range per
+//! parameter, parameters can be locked, and the intensity limits how far values move away from the
+//! current settings.
+
+use nih_plug::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{envelope, lfo, TapeParams};
+
+/// Parameters the randomizer never touches. Randomizing the Morph controls would fight with the
+/// stored snapshots.
+const EXCLUDED_IDS: &[&str] = &["morph", "morph_active", "morph_switch"];
+/// Parameters that start out locked. Tape Only bypasses the delay line and Resonator hands the
+/// delay time over to MIDI notes, which is rarely what someone asking for new echoes wants. A
+/// random custom division would mostly land on odd fractions like 17/43, and a random Shift Mix
+/// would turn most rolls into a shimmer.
+const LOCKED_BY_DEFAULT_IDS: &[&str] =
+    &["distortion_mode", "resonator", "custom_division", "pitch_mix"];
+
+/// The randomizer never pushes feedback past this point on its own.
+const MAX_FEEDBACK: f32 = 0.95;
+/// Gain at and above which the feedback needs to be kept in check.
+const HIGH_GAIN: f32 = 7.0;
+/// The maximum feedback allowed together with a high gain setting. Any higher and the loop turns
+/// into a wall of saturated noise.
+const MAX_FEEDBACK_AT_HIGH_GAIN: f32 = 0.8;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RandomizerSettings {
+    /// How far values are allowed to move away from the current settings, as a fraction of each
+    /// parameter's range.
+    pub intensity: f32,
+    pub entries: Vec<RandomizerEntry>,
+}
+
+/// The lock and the normalized range for a single parameter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RandomizerEntry {
+    pub id: String,
+    pub locked: bool,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for RandomizerSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.5,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl RandomizerSettings {
+    /// Adds entries for parameters that don't have one yet, e.g. because the settings were stored
+    /// by an older version of the plugin.
+    pub(crate) fn sync_with(&mut self, params: &TapeParams) {
+        for (id, _, _) in params.param_map() {
+            if EXCLUDED_IDS.contains(&id.as_str()) || self.entries.iter().any(|e| e.id == id) {
+                continue;
+            }
+
+            self.entries.push(RandomizerEntry {
+                // A random LFO or envelope routing would set everything wobbling at once
+                locked: LOCKED_BY_DEFAULT_IDS.contains(&id.as_str())
+                    || envelope::DEPTH_IDS.contains(&id.as_str())
+                    || lfo::depth_ids().any(|depth_id| depth_id == id),
+                id,
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+    }
+
+    /// Generates new normalized values for all unlocked parameters.
+    pub(crate) fn generate(&self, params: &TapeParams, rng: &mut impl Rng) -> Vec<(ParamPtr, f32)> {
+        let mut values: Vec<(String, ParamPtr, f32)> = params
+            .param_map()
+            .into_iter()
+            .filter_map(|(id, ptr, _)| {
+                let entry = self.entries.iter().find(|e| e.id == id && !e.locked)?;
+
+                // SAFETY: The pointers come straight from `params`, which outlives this function
+                let (current, step_count) =
+                    unsafe { (ptr.unmodulated_normalized_value(), ptr.step_count()) };
+                let new_value = if step_count == Some(1) {
+                    // Toggles flip with a chance that grows with the intensity
+                    if rng.gen::<f32>() < self.intensity * 0.5 {
+                        1.0 - current
+                    } else {
+                        current
+                    }
+                } else {
+                    let span = (entry.max - entry.min) * self.intensity;
+                    (current + rng.gen_range(-1.0f32..=1.0) * span).clamp(entry.min, entry.max)
+                };
+
+                Some((id, ptr, new_value))
+            })
+            .collect();
+
+        apply_safety_rules(params, &mut values);
+
+        values
+            .into_iter()
+            .map(|(_, ptr, value)| (ptr, value))
+            .collect()
+    }
+}
+
+/// Keeps the randomized settings from turning into a runaway feedback loop. Feedback is pulled
+/// back when it was randomized, otherwise the gain gets limited instead.
+fn apply_safety_rules(params: &TapeParams, values: &mut [(String, ParamPtr, f32)]) {
+    let find = |values: &[(String, ParamPtr, f32)], id: &str| {
+        values.iter().position(|(value_id, _, _)| value_id == id)
+    };
+    let feedback_index = find(values, "feedback");
+    let gain_index = find(values, "gain");
+
+    let feedback = match feedback_index {
+        Some(index) => {
+            let normalized = &mut values[index].2;
+            *normalized = normalized.min(params.feedback.preview_normalized(MAX_FEEDBACK));
+            params.feedback.preview_plain(*normalized)
+        }
+        None => params.feedback.unmodulated_plain_value(),
+    };
+    let gain = match gain_index {
+        Some(index) => params.gain.preview_plain(values[index].2),
+        None => params.gain.unmodulated_plain_value(),
+    };
+
+    if gain < HIGH_GAIN || feedback <= MAX_FEEDBACK_AT_HIGH_GAIN {
+        return;
+    }
+
+    match (feedback_index, gain_index) {
+        (Some(index), _) => {
+            values[index].2 = params.feedback.preview_normalized(MAX_FEEDBACK_AT_HIGH_GAIN)
+        }
+        (None, Some(index)) => {
+            // Stay just below the high gain threshold
+            values[index].2 = params.gain.preview_normalized(HIGH_GAIN - 0.1)
+        }
+        (None, None) => (),
+    }
+}
@@ -0,0 +1,145 @@
+//! The Resonator mode. Incoming MIDI notes tune the delay line to the period of the note's pitch,
+//! so the feedback loop rings like a plucked string (Karplus-Strong synthesis), with the tape's
+//! saturation and flutter coloring the resonance.
+
+use nih_plug::prelude::*;
+
+/// How far pitch bend reaches in either direction, in semitones.
+const PITCH_BEND_RANGE: f32 = 2.0;
+/// The note the resonator is tuned to until the first note comes in.
+const DEFAULT_NOTE: f32 = 60.0;
+/// How long the input takes to fade in and out at the start and end of a note. Long enough to
+/// avoid clicks, short enough to keep the attack.
+const GATE_SECONDS: f32 = 0.005;
+/// A one-pole glide gets 99% of the way to the new note after this many time constants.
+const GLIDE_TIME_CONSTANTS_TO_99_PCT: f32 = 4.6;
+/// The most notes that are remembered for falling back to when the newest one is released.
+const MAX_HELD_NOTES: usize = 16;
+
+/// The shortest and the longest decay time the Feedback knob covers, in seconds.
+pub const MIN_DECAY_SECONDS: f32 = 0.05;
+pub const MAX_DECAY_SECONDS: f32 = 10.0;
+/// The tape's flutter, as a fraction of the pitch's period.
+pub const FLUTTER_DEPTH: f32 = 0.002;
+/// The largest difference between the left and the right channel's period, as a fraction of the
+/// period, at full stereo width.
+pub const STEREO_SPREAD: f32 = 0.004;
+
+/// Tracks the played notes, the pitch bend and the glide. Only the most recent note sounds.
+pub struct Resonator {
+    /// The notes that are held down, in the order they were pressed.
+    held_notes: Vec<u8>,
+    velocity: f32,
+    bend_semitones: f32,
+    /// The sounding pitch, as a fractional MIDI note number.
+    pitch: f32,
+    target_pitch: f32,
+    has_played: bool,
+    /// The gain for the input going into the loop, following the velocity of the held note.
+    gate: f32,
+    gate_coefficient: f32,
+    sample_rate: f32,
+}
+
+impl Default for Resonator {
+    fn default() -> Self {
+        Self {
+            held_notes: Vec::with_capacity(MAX_HELD_NOTES),
+            velocity: 0.0,
+            bend_semitones: 0.0,
+            pitch: DEFAULT_NOTE,
+            target_pitch: DEFAULT_NOTE,
+            has_played: false,
+            gate: 0.0,
+            gate_coefficient: 1.0,
+            sample_rate: 44100.0,
+        }
+    }
+}
+
+impl Resonator {
+    pub fn reset(&mut self, sample_rate: f32) {
+        self.held_notes.clear();
+        self.gate = 0.0;
+        self.gate_coefficient = 1.0 - (-1.0 / (GATE_SECONDS * sample_rate)).exp();
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn note_on(&mut self, note: u8, velocity: f32) {
+        self.held_notes.retain(|held_note| *held_note != note);
+        if self.held_notes.len() == MAX_HELD_NOTES {
+            self.held_notes.remove(0);
+        }
+        self.held_notes.push(note);
+
+        self.velocity = velocity;
+        self.target_pitch = note as f32;
+        // There's nothing to glide from before the first note
+        if !self.has_played {
+            self.pitch = self.target_pitch;
+            self.has_played = true;
+        }
+    }
+
+    /// Releasing the newest note glides back to the one that was held before it. When no notes
+    /// are left, the input is shut off and the resonance rings out.
+    pub fn note_off(&mut self, note: u8) {
+        self.held_notes.retain(|held_note| *held_note != note);
+        if let Some(previous_note) = self.held_notes.last() {
+            self.target_pitch = *previous_note as f32;
+        }
+    }
+
+    /// `value` is the raw pitch bend in the `[0, 1]` range, with 0.5 in the center.
+    pub fn pitch_bend(&mut self, value: f32) {
+        self.bend_semitones = (value * 2.0 - 1.0) * PITCH_BEND_RANGE;
+    }
+
+    /// Advances the glide and the gate by a single sample. Returns the period of the current pitch
+    /// in samples, and the gain for the input going into the loop.
+    #[inline]
+    pub fn next(&mut self, glide_coefficient: f32) -> (f32, f32) {
+        self.pitch += (self.target_pitch - self.pitch) * glide_coefficient;
+        let target_gate = if self.held_notes.is_empty() { 0.0 } else { self.velocity };
+        self.gate += (target_gate - self.gate) * self.gate_coefficient;
+
+        let frequency = 440.0 * 2.0f32.powf((self.pitch + self.bend_semitones - 69.0) / 12.0);
+        (self.sample_rate / frequency, self.gate)
+    }
+}
+
+/// The one-pole coefficient that glides to a new note in roughly `glide_ms`.
+pub fn glide_coefficient(glide_ms: f32, sample_rate: f32) -> f32 {
+    if glide_ms <= 0.0 {
+        return 1.0;
+    }
+
+    let time_constant = glide_ms / 1000.0 / GLIDE_TIME_CONSTANTS_TO_99_PCT;
+    1.0 - (-1.0 / (time_constant * sample_rate)).exp()
+}
+
+/// The time the resonance takes to fade by 60 dB at a Feedback setting. The range is covered
+/// exponentially, so the knob has as much room for short plucks as for long drones.
+pub fn decay_seconds(feedback: f32) -> f32 {
+    MIN_DECAY_SECONDS * (MAX_DECAY_SECONDS / MIN_DECAY_SECONDS).powf(feedback)
+}
+
+/// The inverse of [`decay_seconds()`].
+pub fn feedback_for_decay(seconds: f32) -> f32 {
+    ((seconds / MIN_DECAY_SECONDS).ln() / (MAX_DECAY_SECONDS / MIN_DECAY_SECONDS).ln())
+        .clamp(0.0, 1.0)
+}
+
+/// The gain for a single trip around the loop that fades the resonance by 60 dB over
+/// `decay_seconds`.
+#[inline]
+pub fn loop_gain(period_seconds: f32, decay_seconds: f32) -> f32 {
+    util::db_to_gain(-60.0 * period_seconds / decay_seconds)
+}
+
+/// The delay the loop's one-pole tone filter adds at low frequencies, in samples. Left
+/// uncorrected, this would tune the higher notes flat.
+#[inline]
+pub fn tone_filter_delay(cutoff: f32) -> f32 {
+    (1.0 - cutoff) / cutoff
+}
@@ -0,0 +1,121 @@
+//! User preferences that apply across projects, like the default window size. These are stored in
+//! `settings.json` in the plugin's configuration directory and shared by all plugin instances in the
+//! process.
+
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::paths;
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// The window scale factors offered on the Settings page.
+pub const GUI_SCALES: &[f64] = &[0.75, 1.0, 1.25, 1.5, 2.0];
+/// The peak meter release rates offered on the Settings page, in dB per second.
+pub const METER_RELEASE_RATES: &[(&str, f32)] = &[("Slow", 12.0), ("Medium", 30.0), ("Fast", 60.0)];
+/// The 0 VU reference levels offered on the Settings page, in dBFS.
+pub const VU_REFERENCE_LEVELS: &[f32] = &[-24.0, -20.0, -18.0, -14.0, -12.0];
+
+/// How the editor shows the output level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MeterStyle {
+    /// The peak and RMS bar meters.
+    Bar,
+    /// Analog VU meters with a needle.
+    Vu,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The scale factor new editor windows open with. Projects that already store a window size
+    /// keep using that.
+    pub gui_scale: f64,
+    pub check_for_updates: bool,
+    /// The name of the factory preset new plugin instances start out with. `None` keeps the
+    /// parameter defaults.
+    pub default_preset: Option<String>,
+    /// The editor's theme. `None` uses the built-in look.
+    pub theme: Option<String>,
+    /// How fast the peak meters fall back, in dB per second.
+    pub meter_release_db_per_second: f32,
+    pub meter_style: MeterStyle,
+    /// The level in dBFS a sine wave needs to peak at to read 0 VU.
+    pub vu_reference_dbfs: f32,
+    /// How much the spectrum analyzer averages over time, from 0 to just below 1.
+    pub spectrum_smoothing: f32,
+    pub spectrum_peak_hold: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            gui_scale: 1.0,
+            check_for_updates: true,
+            default_preset: None,
+            theme: None,
+            meter_release_db_per_second: 30.0,
+            meter_style: MeterStyle::Bar,
+            vu_reference_dbfs: -18.0,
+            spectrum_smoothing: 0.7,
+            spectrum_peak_hold: false,
+        }
+    }
+}
+
+fn shared() -> &'static Mutex<Settings> {
+    static SETTINGS: OnceLock<Mutex<Settings>> = OnceLock::new();
+
+    SETTINGS.get_or_init(|| Mutex::new(load()))
+}
+
+/// Returns the current settings. The settings file is read once per process.
+pub fn current() -> Settings {
+    shared().lock().unwrap().clone()
+}
+
+/// Replaces the current settings and writes them to the settings file.
+pub fn store(settings: Settings) {
+    save(&settings);
+    *shared().lock().unwrap() = settings;
+}
+
+fn settings_file_path() -> Option<PathBuf> {
+    paths::config_dir().map(|dir| dir.join(SETTINGS_FILE_NAME))
+}
+
+fn load() -> Settings {
+    let Some(path) = settings_file_path() else {
+        return Settings::default();
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            nih_log!("Failed to parse '{}', using the defaults: {}", path.display(), e);
+            Settings::default()
+        }),
+        // There is no settings file until the user changes something
+        Err(_) => Settings::default(),
+    }
+}
+
+fn save(settings: &Settings) {
+    let Some(path) = settings_file_path() else {
+        return;
+    };
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| {
+            let json = serde_json::to_string_pretty(settings).map_err(std::io::Error::other)?;
+            fs::write(&path, json)
+        });
+    if let Err(e) = result {
+        nih_log!("Failed to write '{}': {}", path.display(), e);
+    }
+}
@@ -0,0 +1,109 @@
+//! Parameter snapshots, and the A/B comparison slots built on top of them.
+
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::TapeParams;
+
+/// Normalized parameter values keyed by parameter ID.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParamSnapshot {
+    pub values: BTreeMap<String, f32>,
+}
+
+impl ParamSnapshot {
+    /// Captures the current unmodulated value of every parameter in `params`.
+    pub(crate) fn capture(params: &TapeParams) -> Self {
+        let values = params
+            .param_map()
+            .into_iter()
+            // SAFETY: The pointers come straight from `params`, which outlives this function
+            .map(|(id, ptr, _)| (id, unsafe { ptr.unmodulated_normalized_value() }))
+            .collect();
+
+        Self { values }
+    }
+
+    /// Resolves the snapshot to parameter pointers. Parameters that are not part of the snapshot,
+    /// e.g. because they were added in a later version, are left untouched.
+    pub(crate) fn normalized_values(&self, params: &TapeParams) -> Vec<(ParamPtr, f32)> {
+        params
+            .param_map()
+            .into_iter()
+            .filter_map(|(id, ptr, _)| self.values.get(&id).map(|value| (ptr, *value)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AbSlot {
+    #[default]
+    A,
+    B,
+}
+
+/// The two comparison slots. The live parameter values always belong to the active slot, so the
+/// stored snapshot for that slot is only refreshed when switching away from it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AbSlots {
+    pub active: AbSlot,
+    pub a: Option<ParamSnapshot>,
+    pub b: Option<ParamSnapshot>,
+}
+
+impl AbSlots {
+    fn slot_mut(&mut self, slot: AbSlot) -> &mut Option<ParamSnapshot> {
+        match slot {
+            AbSlot::A => &mut self.a,
+            AbSlot::B => &mut self.b,
+        }
+    }
+
+    /// Switches to `slot`. `live` contains the current parameter values, which are stored in the
+    /// previously active slot. Returns the snapshot that should be applied to the parameters, if
+    /// anything needs to change. An empty slot starts out as a copy of the current settings.
+    pub fn select(&mut self, slot: AbSlot, live: ParamSnapshot) -> Option<ParamSnapshot> {
+        if slot == self.active {
+            return None;
+        }
+
+        *self.slot_mut(self.active) = Some(live.clone());
+        self.active = slot;
+        let target = self.slot_mut(slot).get_or_insert(live).clone();
+
+        Some(target)
+    }
+
+    /// Copies the contents of slot A into slot B. Returns the snapshot that should be applied if
+    /// B is the active slot.
+    pub fn copy_a_to_b(&mut self, live: ParamSnapshot) -> Option<ParamSnapshot> {
+        let a = match self.active {
+            AbSlot::A => live,
+            AbSlot::B => self.a.clone().unwrap_or(live),
+        };
+        self.a = Some(a.clone());
+        self.b = Some(a.clone());
+
+        match self.active {
+            AbSlot::A => None,
+            AbSlot::B => Some(a),
+        }
+    }
+
+    /// Exchanges the contents of the two slots while keeping the active slot. Returns the snapshot
+    /// that should be applied, which is the other slot's former contents.
+    pub fn swap(&mut self, live: ParamSnapshot) -> Option<ParamSnapshot> {
+        *self.slot_mut(self.active) = Some(live);
+        std::mem::swap(&mut self.a, &mut self.b);
+
+        let active = self.active;
+        let target = self.slot_mut(active).clone();
+        if target.is_none() {
+            // The other slot was still empty, so there is nothing to swap in yet
+            std::mem::swap(&mut self.a, &mut self.b);
+        }
+
+        target
+    }
+}
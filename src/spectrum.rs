@@ -0,0 +1,220 @@
+//! The spectrum analyzer. The audio thread only copies the dry input and the wet signal into a
+//! lock-free ring buffer, and the editor runs the FFT on its own thread whenever it redraws.
+
+use nih_plug::prelude::*;
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of samples going into each FFT.
+pub const FFT_SIZE: usize = 2048;
+/// The ring holds twice as many samples as the FFT needs, so the audio thread is always writing
+/// well away from the part the editor is reading.
+const RING_SIZE: usize = FFT_SIZE * 2;
+
+/// The quietest level the analyzer shows.
+pub const MIN_DB: f32 = -100.0;
+
+/// Mono versions of the dry input and the wet signal.
+pub struct SpectrumRing {
+    dry: Box<[AtomicF32]>,
+    wet: Box<[AtomicF32]>,
+    /// The total number of samples written so far. Only the lower bits are used as the index.
+    write_pos: AtomicUsize,
+}
+
+impl Default for SpectrumRing {
+    fn default() -> Self {
+        Self {
+            dry: (0..RING_SIZE).map(|_| AtomicF32::new(0.0)).collect(),
+            wet: (0..RING_SIZE).map(|_| AtomicF32::new(0.0)).collect(),
+            write_pos: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl SpectrumRing {
+    /// Writes a single sample. The new samples only become visible to the editor after
+    /// [`commit()`][Self::commit()] has been called with the updated position.
+    #[inline]
+    pub fn write(&self, pos: usize, dry: f32, wet: f32) {
+        let index = pos % RING_SIZE;
+        self.dry[index].store(dry, Ordering::Relaxed);
+        self.wet[index].store(wet, Ordering::Relaxed);
+    }
+
+    pub fn write_pos(&self) -> usize {
+        self.write_pos.load(Ordering::Acquire)
+    }
+
+    /// Publishes everything written up to `pos`.
+    pub fn commit(&self, pos: usize) {
+        self.write_pos.store(pos, Ordering::Release);
+    }
+
+    /// Copies the most recent [`FFT_SIZE`] samples of both signals.
+    fn read_latest(&self, dry: &mut [f32], wet: &mut [f32]) {
+        let end = self.write_pos();
+        for (i, (dry, wet)) in dry.iter_mut().zip(wet.iter_mut()).enumerate() {
+            let index = end.wrapping_add(RING_SIZE - FFT_SIZE + i) % RING_SIZE;
+            *dry = self.dry[index].load(Ordering::Relaxed);
+            *wet = self.wet[index].load(Ordering::Relaxed);
+        }
+    }
+}
+
+/// Turns the ring buffer's contents into smoothed magnitude spectra. Every spectrum holds one
+/// level in decibels for each of the `FFT_SIZE / 2 + 1` bins.
+pub struct SpectrumAnalyzer {
+    window: Vec<f32>,
+    /// Scales the magnitudes so a full scale sine wave reads 0 dB.
+    window_gain: f32,
+    samples_dry: Vec<f32>,
+    samples_wet: Vec<f32>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    pub dry_db: Vec<f32>,
+    pub wet_db: Vec<f32>,
+    pub dry_peak_db: Vec<f32>,
+    pub wet_peak_db: Vec<f32>,
+}
+
+impl Default for SpectrumAnalyzer {
+    fn default() -> Self {
+        let window: Vec<f32> = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        let window_gain = 2.0 / window.iter().sum::<f32>();
+        let num_bins = FFT_SIZE / 2 + 1;
+
+        Self {
+            window,
+            window_gain,
+            samples_dry: vec![0.0; FFT_SIZE],
+            samples_wet: vec![0.0; FFT_SIZE],
+            re: vec![0.0; FFT_SIZE],
+            im: vec![0.0; FFT_SIZE],
+            dry_db: vec![MIN_DB; num_bins],
+            wet_db: vec![MIN_DB; num_bins],
+            dry_peak_db: vec![MIN_DB; num_bins],
+            wet_peak_db: vec![MIN_DB; num_bins],
+        }
+    }
+}
+
+impl SpectrumAnalyzer {
+    /// Analyzes the latest samples. `smoothing` goes from 0 for no smoothing at all to just below
+    /// 1 for a very slow response. The peaks are held for as long as `peak_hold` is enabled.
+    pub fn update(&mut self, ring: &SpectrumRing, smoothing: f32, peak_hold: bool) {
+        ring.read_latest(&mut self.samples_dry, &mut self.samples_wet);
+
+        let smoothing = smoothing.clamp(0.0, 0.99);
+        for is_wet in [false, true] {
+            let samples = if is_wet { &self.samples_wet } else { &self.samples_dry };
+            for (i, sample) in samples.iter().enumerate() {
+                self.re[i] = sample * self.window[i];
+                self.im[i] = 0.0;
+            }
+            fft(&mut self.re, &mut self.im);
+
+            let (levels, peaks) = if is_wet {
+                (&mut self.wet_db, &mut self.wet_peak_db)
+            } else {
+                (&mut self.dry_db, &mut self.dry_peak_db)
+            };
+            for (bin, (level, peak)) in levels.iter_mut().zip(peaks.iter_mut()).enumerate() {
+                let magnitude = (self.re[bin].powi(2) + self.im[bin].powi(2)).sqrt();
+                let db = util::gain_to_db(magnitude * self.window_gain).max(MIN_DB);
+                *level = *level * smoothing + db * (1.0 - smoothing);
+                *peak = if peak_hold { peak.max(*level) } else { *level };
+            }
+        }
+    }
+}
+
+/// An in-place iterative radix-2 FFT. Both slices need to have the same power of two length.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut cur_re, mut cur_im) = (1.0f32, 0.0f32);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N: usize = 256;
+
+    fn magnitudes(re: &[f32], im: &[f32]) -> Vec<f32> {
+        re.iter().zip(im).map(|(re, im)| (re.powi(2) + im.powi(2)).sqrt()).collect()
+    }
+
+    #[test]
+    fn sine_peaks_in_its_bin() {
+        let bin = 37;
+        let mut re: Vec<f32> =
+            (0..N).map(|i| (2.0 * PI * bin as f32 * i as f32 / N as f32).sin()).collect();
+        let mut im = vec![0.0; N];
+        fft(&mut re, &mut im);
+
+        let magnitudes = magnitudes(&re, &im);
+        let peak = (0..=N / 2).max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b])).unwrap();
+        assert_eq!(peak, bin);
+        assert!((magnitudes[bin] - N as f32 / 2.0).abs() < 1e-2);
+        for (i, magnitude) in magnitudes.iter().enumerate().take(N / 2 + 1) {
+            if i != bin {
+                assert!(*magnitude < 1e-2, "bin {i} has a magnitude of {magnitude}");
+            }
+        }
+    }
+
+    #[test]
+    fn dc_only_lands_in_the_first_bin() {
+        let mut re = vec![0.5; N];
+        let mut im = vec![0.0; N];
+        fft(&mut re, &mut im);
+
+        let magnitudes = magnitudes(&re, &im);
+        assert!((magnitudes[0] - 0.5 * N as f32).abs() < 1e-3);
+        for (i, magnitude) in magnitudes.iter().enumerate().skip(1) {
+            assert!(*magnitude < 1e-3, "bin {i} has a magnitude of {magnitude}");
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Tap tempo. The time between taps sets the delay time, or the tempo Time Sync follows when the
+//! host doesn't provide one. Taps come from the editor's Tap button and from a MIDI note, and are
+//! all timed on the audio thread.
+
+/// A pause longer than this starts a new series of taps.
+const TAP_TIMEOUT_SECONDS: f64 = 2.0;
+/// The number of intervals between taps that are averaged.
+const MAX_INTERVALS: usize = 4;
+/// Intervals that are further than this fraction away from the median of the recent intervals
+/// are left out of the average, so a single early or late tap doesn't throw it off.
+const OUTLIER_TOLERANCE: f64 = 0.2;
+/// The fastest tempo taps can set. The timeout sets the slowest one.
+const MAX_TEMPO: f64 = 300.0;
+
+#[derive(Default)]
+pub struct TapTempo {
+    last_tap: Option<f64>,
+    /// The most recent intervals between taps in seconds, oldest first.
+    intervals: [f64; MAX_INTERVALS],
+    num_intervals: usize,
+}
+
+impl TapTempo {
+    /// Registers a tap at `time` seconds. Returns the averaged interval between the recent taps in
+    /// seconds, starting with the second tap of a series.
+    pub fn tap(&mut self, time: f64) -> Option<f64> {
+        let interval = time - self.last_tap.replace(time)?;
+        if interval <= 0.0 {
+            // The button and the MIDI note hit at the same time
+            return None;
+        }
+        if interval > TAP_TIMEOUT_SECONDS {
+            self.num_intervals = 0;
+            return None;
+        }
+
+        if self.num_intervals == MAX_INTERVALS {
+            self.intervals.rotate_left(1);
+            self.num_intervals -= 1;
+        }
+        self.intervals[self.num_intervals] = interval;
+        self.num_intervals += 1;
+
+        Some(self.average_interval())
+    }
+
+    /// The average of the intervals that are close to their median. The median itself always
+    /// counts, so there's at least one.
+    fn average_interval(&self) -> f64 {
+        let intervals = &self.intervals[..self.num_intervals];
+        let mut sorted = self.intervals;
+        let sorted = &mut sorted[..self.num_intervals];
+        sorted.sort_by(f64::total_cmp);
+        let median = sorted[sorted.len() / 2];
+
+        let (sum, count) = intervals
+            .iter()
+            .filter(|interval| (*interval - median).abs() <= median * OUTLIER_TOLERANCE)
+            .fold((0.0, 0), |(sum, count), interval| (sum + interval, count + 1));
+        sum / count as f64
+    }
+}
+
+/// How far the Time parameter may move before a tapped delay time counts as taken over.
+const TAKEOVER_TOLERANCE: f32 = 1e-4;
+
+/// A delay time set by tapping with Time Sync off. The audio thread uses it right away, so taps
+/// work with the editor closed, and holds it until the Time parameter moves. That happens when
+/// the editor applies the tapped time to the parameter, or when Time is changed some other way.
+#[derive(Debug, Clone, Copy)]
+pub struct TappedTime {
+    pub delay_ms: f32,
+    /// The Time parameter's normalized value when the tap came in.
+    time_normalized: f32,
+}
+
+impl TappedTime {
+    pub fn new(delay_ms: f32, time_normalized: f32) -> Self {
+        Self { delay_ms, time_normalized }
+    }
+
+    /// Whether the Time parameter is still where it was when the tap came in.
+    pub fn is_current(&self, time_normalized: f32) -> bool {
+        (time_normalized - self.time_normalized).abs() <= TAKEOVER_TOLERANCE
+    }
+}
+
+/// The tempo with one beat per tap interval.
+pub fn tempo(interval_seconds: f64) -> f32 {
+    (60.0 / interval_seconds).min(MAX_TEMPO) as f32
+}
@@ -0,0 +1,149 @@
+//! A decimated overview of the delay buffer for the editor's tape display. The audio thread
+//! publishes a peak value per bin together with the head positions, all through atomics so neither
+//! side ever has to wait for the other.
+
+use nih_plug::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// The number of bins the delay buffer is divided into.
+pub const NUM_BINS: usize = 256;
+
+/// The shared overview. Positions are stored as fractions of the buffer length.
+pub struct TapeOverview {
+    peaks_l: [AtomicF32; NUM_BINS],
+    peaks_r: [AtomicF32; NUM_BINS],
+    write_pos: AtomicF32,
+    read_pos_l: AtomicF32,
+    read_pos_r: AtomicF32,
+    /// Whether the delay line is running. It's bypassed in Tape Only mode.
+    is_active: AtomicBool,
+    /// Incremented every time the audio thread publishes a new block, so the editor knows when to
+    /// redraw.
+    revision: AtomicU32,
+}
+
+impl Default for TapeOverview {
+    fn default() -> Self {
+        Self {
+            peaks_l: std::array::from_fn(|_| AtomicF32::new(0.0)),
+            peaks_r: std::array::from_fn(|_| AtomicF32::new(0.0)),
+            write_pos: AtomicF32::new(0.0),
+            read_pos_l: AtomicF32::new(0.0),
+            read_pos_r: AtomicF32::new(0.0),
+            is_active: AtomicBool::new(false),
+            revision: AtomicU32::new(0),
+        }
+    }
+}
+
+impl TapeOverview {
+    pub fn peak_l(&self, bin: usize) -> f32 {
+        self.peaks_l[bin].load(Ordering::Relaxed)
+    }
+
+    pub fn peak_r(&self, bin: usize) -> f32 {
+        self.peaks_r[bin].load(Ordering::Relaxed)
+    }
+
+    pub fn write_pos(&self) -> f32 {
+        self.write_pos.load(Ordering::Relaxed)
+    }
+
+    pub fn read_pos_l(&self) -> f32 {
+        self.read_pos_l.load(Ordering::Relaxed)
+    }
+
+    pub fn read_pos_r(&self) -> f32 {
+        self.read_pos_r.load(Ordering::Relaxed)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active.load(Ordering::Relaxed)
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision.load(Ordering::Relaxed)
+    }
+}
+
+/// The audio thread's side of the overview. Bins are updated incrementally as the write head moves
+/// through them, and the whole overview is only rebuilt from scratch when it may be out of date.
+#[derive(Debug, Default)]
+pub struct TapeOverviewWriter {
+    bin: usize,
+    peak_l: f32,
+    peak_r: f32,
+    /// Set when the overview no longer matches the buffer, e.g. because nothing was published
+    /// while the editor was closed or because the buffer was cleared.
+    is_stale: bool,
+}
+
+impl TapeOverviewWriter {
+    /// Marks the overview for a full rebuild the next time it gets published.
+    pub fn invalidate(&mut self) {
+        self.is_stale = true;
+    }
+
+    /// Rebuilds the overview from the delay buffers if it may be out of date.
+    pub fn prepare(&mut self, overview: &TapeOverview, buffer_l: &[f32], buffer_r: &[f32]) {
+        if !self.is_stale {
+            return;
+        }
+
+        let bin_size = buffer_l.len().div_ceil(NUM_BINS).max(1);
+        for (bin, (chunk_l, chunk_r)) in
+            buffer_l.chunks(bin_size).zip(buffer_r.chunks(bin_size)).enumerate()
+        {
+            let peak = |chunk: &[f32]| chunk.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            overview.peaks_l[bin].store(peak(chunk_l), Ordering::Relaxed);
+            overview.peaks_r[bin].store(peak(chunk_r), Ordering::Relaxed);
+        }
+
+        self.peak_l = 0.0;
+        self.peak_r = 0.0;
+        self.is_stale = false;
+    }
+
+    /// Records the samples that were just written to the delay buffers at `write_pos`.
+    #[inline]
+    pub fn record(
+        &mut self,
+        overview: &TapeOverview,
+        write_pos: usize,
+        buffer_len: usize,
+        sample_l: f32,
+        sample_r: f32,
+    ) {
+        let bin_size = buffer_len.div_ceil(NUM_BINS).max(1);
+        let bin = (write_pos / bin_size).min(NUM_BINS - 1);
+        if bin != self.bin {
+            // The write head moved on, so this bin's old contents are being overwritten
+            self.bin = bin;
+            self.peak_l = 0.0;
+            self.peak_r = 0.0;
+        }
+
+        self.peak_l = self.peak_l.max(sample_l.abs());
+        self.peak_r = self.peak_r.max(sample_r.abs());
+        overview.peaks_l[bin].store(self.peak_l, Ordering::Relaxed);
+        overview.peaks_r[bin].store(self.peak_r, Ordering::Relaxed);
+    }
+
+    /// Publishes the head positions at the end of a block.
+    pub fn publish(
+        &self,
+        overview: &TapeOverview,
+        is_active: bool,
+        buffer_len: usize,
+        write_pos: usize,
+        read_pos_l: f32,
+        read_pos_r: f32,
+    ) {
+        let buffer_len = buffer_len.max(1) as f32;
+        overview.write_pos.store(write_pos as f32 / buffer_len, Ordering::Relaxed);
+        overview.read_pos_l.store(read_pos_l / buffer_len, Ordering::Relaxed);
+        overview.read_pos_r.store(read_pos_r / buffer_len, Ordering::Relaxed);
+        overview.is_active.store(is_active, Ordering::Relaxed);
+        overview.revision.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,117 @@
+//! Follows the host's transport, so the tape can be cleared when playback starts, tails can be cut
+//! when it stops, and the flutter can follow the song position. With all of that, the same bar
+//! sounds the same on every playback pass.
+
+use nih_plug::prelude::*;
+use std::sync::atomic::Ordering;
+
+use crate::TapeParams;
+
+/// How far the song position may be off from where the previous block ended before it counts as
+/// a jump, in samples. Some hosts round their positions.
+const JUMP_TOLERANCE_SAMPLES: i64 = 2;
+/// How long the tape fades out before it gets cleared. Clearing it outright would click.
+const CLEAR_FADE_SECONDS: f32 = 0.01;
+
+/// What the plugin does when the transport changes. These are stored with the project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportOptions {
+    /// Clears the tape when playback starts, and when the host jumps, e.g. at the end of a loop.
+    pub clear_on_play: bool,
+    /// Lets the echoes ring out when playback stops. Otherwise they get cleared.
+    pub keep_tails_on_stop: bool,
+    /// Locks the flutter to the song position while playing.
+    pub phase_lock: bool,
+}
+
+impl TransportOptions {
+    pub(crate) fn load(params: &TapeParams) -> Self {
+        Self {
+            clear_on_play: params.clear_on_play.load(Ordering::Relaxed),
+            keep_tails_on_stop: params.keep_tails_on_stop.load(Ordering::Relaxed),
+            phase_lock: params.phase_lock.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn store(&self, params: &TapeParams) {
+        params.clear_on_play.store(self.clear_on_play, Ordering::Relaxed);
+        params.keep_tails_on_stop.store(self.keep_tails_on_stop, Ordering::Relaxed);
+        params.phase_lock.store(self.phase_lock, Ordering::Relaxed);
+    }
+}
+
+/// What happened to the transport since the previous block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportChange {
+    None,
+    Started,
+    Stopped,
+    /// The song position jumped while playing, e.g. when the host loops.
+    Jumped,
+}
+
+#[derive(Default)]
+pub struct TransportTracker {
+    was_playing: bool,
+    /// Where the next block should start if the host keeps playing.
+    expected_pos_samples: Option<i64>,
+}
+
+impl TransportTracker {
+    /// Called at the start of every block with the host's transport and the block's length.
+    pub fn update(&mut self, transport: &Transport, num_samples: usize) -> TransportChange {
+        let pos_samples = transport.pos_samples();
+        let change = match (self.was_playing, transport.playing) {
+            (false, true) => TransportChange::Started,
+            (true, false) => TransportChange::Stopped,
+            (true, true) => match (self.expected_pos_samples, pos_samples) {
+                (Some(expected), Some(pos)) if (pos - expected).abs() > JUMP_TOLERANCE_SAMPLES => {
+                    TransportChange::Jumped
+                }
+                _ => TransportChange::None,
+            },
+            (false, false) => TransportChange::None,
+        };
+
+        self.was_playing = transport.playing;
+        self.expected_pos_samples = pos_samples.map(|pos| pos + num_samples as i64);
+        change
+    }
+}
+
+/// Fades the tape's playback out before the tape gets cleared.
+#[derive(Default)]
+pub struct TapeClearFade {
+    length: usize,
+    remaining: usize,
+}
+
+impl TapeClearFade {
+    pub fn reset(&mut self, sample_rate: f32) {
+        self.length = ((CLEAR_FADE_SECONDS * sample_rate) as usize).max(1);
+        self.remaining = 0;
+    }
+
+    /// The length of the fade in samples. The input recorded during the fade survives the clear.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn start(&mut self) {
+        if self.remaining == 0 {
+            self.remaining = self.length;
+        }
+    }
+
+    /// Advances the fade by a single sample. Returns the gain for the tape's playback, and whether
+    /// the fade just ended and the tape should be cleared now.
+    #[inline]
+    pub fn next(&mut self) -> (f32, bool) {
+        if self.remaining == 0 {
+            return (1.0, false);
+        }
+
+        self.remaining -= 1;
+        (self.remaining as f32 / self.length as f32, self.remaining == 0)
+    }
+}
@@ -0,0 +1,256 @@
+//! Checks GitHub for new releases. The check runs once per process, or once the user opts back in
+//! if it was skipped, and goes online at most once per day. The last successful result is cached
+//! in the configuration directory so the release notes are still available when the check is
+//! skipped. The opt-out is part of the user's settings.
+
+use nih_plug::prelude::*;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{paths, settings};
+
+const DEFAULT_ENDPOINT: &str =
+    "https://api.github.com/repos/minburg/vst-tape-delay/releases/latest";
+/// Overrides the endpoint, e.g. to point the check at a local mock server.
+const ENDPOINT_ENV_VAR: &str = "TAPE_DELAY_UPDATE_URL";
+const STATE_FILE_NAME: &str = "update_check.json";
+
+/// Releases are looked up at most this often. In between, the cached result is used.
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The parts of a GitHub release the editor shows.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The release notes, in Markdown.
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub html_url: Option<String>,
+}
+
+/// Everything stored in the update check's state file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct CheckState {
+    /// Takes the place of the default endpoint. The environment variable takes precedence.
+    endpoint: Option<String>,
+    /// The endpoint the cached result came from. The cache is ignored when the endpoint changes.
+    checked_endpoint: Option<String>,
+    /// Seconds since the Unix epoch.
+    last_checked: u64,
+    latest: Option<Release>,
+}
+
+/// The result of the update check, shared between all plugin instances in the process.
+#[derive(Debug)]
+pub struct UpdateStatus {
+    current_version: &'static str,
+    enabled: AtomicBool,
+    available: AtomicBool,
+    latest: Mutex<Option<Release>>,
+    check_started: AtomicBool,
+}
+
+/// Returns the process-wide update status. `current_version` is only used the first time this is
+/// called.
+pub fn shared_status(current_version: &'static str) -> Arc<UpdateStatus> {
+    static STATUS: OnceLock<Arc<UpdateStatus>> = OnceLock::new();
+
+    STATUS
+        .get_or_init(|| {
+            Arc::new(UpdateStatus {
+                current_version,
+                enabled: AtomicBool::new(settings::current().check_for_updates),
+                available: AtomicBool::new(false),
+                latest: Mutex::new(None),
+                check_started: AtomicBool::new(false),
+            })
+        })
+        .clone()
+}
+
+impl UpdateStatus {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_update_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
+
+    /// The newest release, if it is newer than the running version.
+    pub fn latest_release(&self) -> Option<Release> {
+        if self.is_update_available() {
+            self.latest.lock().unwrap().clone()
+        } else {
+            None
+        }
+    }
+
+    /// Starts the update check on a background thread, unless it has already been started in this
+    /// process.
+    pub fn check(self: &Arc<Self>) {
+        if self.check_started.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let status = self.clone();
+        thread::spawn(move || status.run_check());
+    }
+
+    /// Follows a change to the opt-out in the settings. Enabling the check again shows the result of
+    /// the check that already ran in this process, or starts the check if it was skipped. Only the
+    /// check's thread resets `check_started`, so there's never more than one check running.
+    pub fn set_enabled(self: &Arc<Self>, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+
+        if enabled {
+            // `latest` only holds releases that are newer than this version
+            if self.latest.lock().unwrap().is_some() {
+                self.available.store(true, Ordering::Relaxed);
+            }
+            self.check();
+        } else {
+            self.available.store(false, Ordering::Relaxed);
+        }
+    }
+
+    fn run_check(&self) {
+        if !self.is_enabled() {
+            nih_log!("Update check is disabled.");
+            // Allow the editor to start the check again when the user opts back in
+            self.check_started.store(false, Ordering::Release);
+            return;
+        }
+
+        let mut state = load_state();
+        let endpoint = std::env::var(ENDPOINT_ENV_VAR)
+            .ok()
+            .or_else(|| state.endpoint.clone())
+            .unwrap_or_else(|| DEFAULT_ENDPOINT.to_owned());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+        let is_cache_fresh = state.checked_endpoint.as_deref() == Some(endpoint.as_str())
+            && now.saturating_sub(state.last_checked) < CHECK_INTERVAL.as_secs();
+
+        if is_cache_fresh {
+            nih_log!("Using the cached update check result.");
+        } else {
+            nih_log!("Checking for updates in the background...");
+            match fetch_latest_release(&endpoint) {
+                Ok(release) => {
+                    state.latest = Some(release);
+                    state.checked_endpoint = Some(endpoint);
+                    state.last_checked = now;
+                    save_state(&state);
+                }
+                // Keep the previous result around. The next process tries again.
+                Err(e) => nih_log!("Update check failed: {}", e),
+            }
+        }
+
+        if let Some(release) = state.latest {
+            if is_newer(&release.tag_name, self.current_version) {
+                nih_log!("New version available: {}", release.tag_name);
+                *self.latest.lock().unwrap() = Some(release);
+                self.available.store(self.is_enabled(), Ordering::Relaxed);
+            } else {
+                nih_log!("Plugin is up to date.");
+            }
+        }
+    }
+}
+
+fn fetch_latest_release(endpoint: &str) -> Result<Release, String> {
+    let client = Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .get(endpoint)
+        .header(reqwest::header::USER_AGENT, "nih-plug-update-checker") // GitHub requires this
+        .send()
+        .map_err(|e| format!("request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("request failed with status: {}", response.status()));
+    }
+
+    response
+        .json::<Release>()
+        .map_err(|e| format!("failed to parse the response: {}", e))
+}
+
+/// Compares two version strings as semantic versions. Tags may carry a leading `v`. Returns `false`
+/// if either version cannot be parsed.
+fn is_newer(latest: &str, current: &str) -> bool {
+    let parse = |version: &str| semver::Version::parse(version.trim().trim_start_matches('v'));
+
+    match (parse(latest), parse(current)) {
+        (Ok(latest), Ok(current)) => latest > current,
+        (Err(e), _) | (_, Err(e)) => {
+            nih_log!("Failed to compare versions '{}' and '{}': {}", latest, current, e);
+            false
+        }
+    }
+}
+
+fn state_file_path() -> Option<PathBuf> {
+    paths::config_dir().map(|dir| dir.join(STATE_FILE_NAME))
+}
+
+fn load_state() -> CheckState {
+    state_file_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &CheckState) {
+    let Some(path) = state_file_path() else {
+        return;
+    };
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| {
+            let json = serde_json::to_string_pretty(state).map_err(std::io::Error::other)?;
+            fs::write(&path, json)
+        });
+    if let Err(e) = result {
+        nih_log!("Failed to write '{}': {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_versions_semantically() {
+        assert!(is_newer("v0.1.13", "0.1.12"));
+        assert!(is_newer("0.2.0", "0.1.13"));
+        assert!(is_newer("0.1.10", "0.1.9"));
+        assert!(!is_newer("v0.1.12", "0.1.12"));
+        assert!(!is_newer("0.1.11", "0.1.12"));
+        assert!(!is_newer("0.2.0-beta.1", "0.2.0"));
+    }
+
+    #[test]
+    fn unparsable_versions_are_never_newer() {
+        assert!(!is_newer("latest", "0.1.12"));
+        assert!(!is_newer("0.1.13", ""));
+    }
+}
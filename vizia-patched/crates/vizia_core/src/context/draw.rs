@@ -387,6 +387,22 @@ impl<'a> DrawContext<'a> {
         self.style.box_shadow.get(self.current)
     }
 
+    /// Returns the numbers in the value of a custom property like `--meter-min` for the current
+    /// view. Like in CSS, custom properties are inherited, so a value set on an ancestor applies
+    /// as well.
+    pub fn custom_property(&self, name: &str) -> Option<&[f32]> {
+        let custom_property = self.style.custom_properties.get(name)?;
+        let mut entity = Some(self.current);
+        while let Some(current) = entity {
+            if let Some(value) = custom_property.get(current) {
+                return Some(value);
+            }
+            entity = self.tree.get_parent(current);
+        }
+
+        None
+    }
+
     pub fn backdrop_filter(&self) -> Option<&Filter> {
         self.style.backdrop_filter.get(self.current)
     }
@@ -741,6 +741,41 @@ impl<'a> EventContext<'a> {
         Ok(())
     }
 
+    /// Loads an image, replacing any image that was previously loaded under the same name.
+    pub fn load_image(
+        &mut self,
+        path: &str,
+        image: image::DynamicImage,
+        policy: crate::resource::ImageRetentionPolicy,
+    ) {
+        use crate::resource::{ImageOrId, StoredImage};
+        use std::collections::hash_map::Entry;
+
+        match self.resource_manager.images.entry(path.to_string()) {
+            Entry::Occupied(mut occ) => {
+                occ.get_mut().image = ImageOrId::Image(
+                    image,
+                    femtovg::ImageFlags::REPEAT_X | femtovg::ImageFlags::REPEAT_Y,
+                );
+                occ.get_mut().dirty = true;
+                occ.get_mut().retention_policy = policy;
+            }
+            Entry::Vacant(vac) => {
+                vac.insert(StoredImage {
+                    image: ImageOrId::Image(
+                        image,
+                        femtovg::ImageFlags::REPEAT_X | femtovg::ImageFlags::REPEAT_Y,
+                    ),
+                    retention_policy: policy,
+                    used: true,
+                    dirty: false,
+                    observers: HashSet::new(),
+                });
+            }
+        }
+        self.style.needs_relayout();
+    }
+
     /// Spawns a thread and provides a [ContextProxy] for sending events back to the main thread.
     pub fn spawn<F>(&self, target: F)
     where
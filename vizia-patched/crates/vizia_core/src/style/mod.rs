@@ -75,13 +75,11 @@ pub use vizia_style::{
     CursorIcon, Display, Filter, FontFamily, FontSize, FontStretch, FontStyle, FontWeight,
     FontWeightKeyword, GenericFontFamily, Gradient, HorizontalPosition, HorizontalPositionKeyword,
     Length, LengthOrPercentage, LengthValue, LineDirection, LinearGradient, Matrix, Opacity,
-    Overflow, PointerEvents, Position, Scale, TextAlign, Transform, Transition, Translate,
-    VerticalPosition, VerticalPositionKeyword, Visibility, RGBA,
+    Overflow, ParserOptions, PointerEvents, Position, Scale, StyleSheet, TextAlign, Transform,
+    Transition, Translate, VerticalPosition, VerticalPositionKeyword, Visibility, RGBA,
 };
 
-use vizia_style::{
-    EasingFunction, KeyframeSelector, ParserOptions, Property, SelectorList, Selectors, StyleSheet,
-};
+use vizia_style::{EasingFunction, KeyframeSelector, Property, SelectorList, Selectors};
 
 mod rule;
 pub(crate) use rule::Rule;
@@ -258,6 +256,10 @@ pub struct Style {
 
     pub(crate) pointer_events: StyleSet<PointerEvents>,
 
+    // Custom properties like `--meter-min: -60;`, keyed by name. Only the numbers in a property's
+    // value are kept.
+    pub(crate) custom_properties: FnvHashMap<String, StyleSet<Vec<f32>>>,
+
     // LAYOUT
 
     // Layout Type
@@ -1537,9 +1539,16 @@ impl Style {
                 warn!("Unparsed: {}", unparsed.name);
             }
 
-            // TODO: Custom property support
             Property::Custom(custom) => {
-                warn!("Custom Property: {}", custom.name);
+                let numbers = custom.value.numbers();
+                if numbers.is_empty() {
+                    warn!("Custom Property: {}", custom.name);
+                } else {
+                    self.custom_properties
+                        .entry(custom.name.to_string())
+                        .or_default()
+                        .insert_rule(rule_id, numbers);
+                }
             }
 
             _ => {}
@@ -1580,6 +1589,10 @@ impl Style {
 
     // Remove style data for the given entity.
     pub fn remove(&mut self, entity: Entity) {
+        for custom_property in self.custom_properties.values_mut() {
+            custom_property.remove(entity);
+        }
+
         self.ids.remove(entity);
         self.classes.remove(entity);
         self.pseudo_classes.remove(entity);
@@ -1734,6 +1747,10 @@ impl Style {
 
     // Remove all shared style data.
     pub fn clear_style_rules(&mut self) {
+        for custom_property in self.custom_properties.values_mut() {
+            custom_property.clear_rules();
+        }
+
         self.disabled.clear_rules();
         // Display
         self.display.clear_rules();
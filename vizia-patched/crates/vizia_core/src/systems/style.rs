@@ -585,6 +585,12 @@ fn link_style_data(style: &mut Style, entity: Entity, matched_rules: &[Rule]) {
         should_redraw = true;
     }
 
+    for custom_property in style.custom_properties.values_mut() {
+        if custom_property.link(entity, matched_rules) {
+            should_redraw = true;
+        }
+    }
+
     //
     if should_relayout {
         style.system_flags.set(SystemFlags::RELAYOUT, true);
@@ -69,6 +69,18 @@ impl<'i> TokenOrValue<'i> {
 }
 
 impl<'i> TokenList<'i> {
+    /// Returns the values of all number and dimension tokens in the list, in order.
+    pub fn numbers(&self) -> Vec<f32> {
+        self.0
+            .iter()
+            .filter_map(|token| match token {
+                TokenOrValue::Token(Token::Number { value, .. })
+                | TokenOrValue::Token(Token::Dimension { value, .. }) => Some(*value),
+                _ => None,
+            })
+            .collect()
+    }
+
     fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, CustomParseError<'i>>> {
         input.parse_until_before(Delimiter::Bang | Delimiter::Semicolon, |input| {
             let mut tokens = vec![];